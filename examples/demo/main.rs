@@ -13,28 +13,28 @@ fn main()
 
     let (device, ctx, queue) = opencl::util::create_compute_context().unwrap();
 
-    println!("{}", device.name());
+    println!("{}", device.name().unwrap());
 
-    let a: CLBuffer<isize> = ctx.create_buffer(vec_a.len(), opencl::cl::CL_MEM_READ_ONLY);
-    let b: CLBuffer<isize> = ctx.create_buffer(vec_a.len(), opencl::cl::CL_MEM_READ_ONLY);
-    let c: CLBuffer<isize> = ctx.create_buffer(vec_a.len(), opencl::cl::CL_MEM_WRITE_ONLY);
+    let a: CLBuffer<isize> = ctx.create_buffer(vec_a.len(), opencl::cl::CL_MEM_READ_ONLY).unwrap();
+    let b: CLBuffer<isize> = ctx.create_buffer(vec_a.len(), opencl::cl::CL_MEM_READ_ONLY).unwrap();
+    let c: CLBuffer<isize> = ctx.create_buffer(vec_a.len(), opencl::cl::CL_MEM_WRITE_ONLY).unwrap();
 
-    queue.write(&a, &&vec_a[..], ());
-    queue.write(&b, &&vec_b[..], ());
+    queue.write(&a, &&vec_a[..], ()).unwrap();
+    queue.write(&b, &&vec_b[..], ()).unwrap();
 
-    let program = ctx.create_program_from_source(ker);
+    let program = ctx.create_program_from_source(ker).unwrap();
     program.build(&device).ok().expect("Couldn't build program.");
 
 
-    let kernel = program.create_kernel("vector_add");
+    let kernel = program.create_kernel("vector_add").unwrap();
 
-    kernel.set_arg(0, &a);
-    kernel.set_arg(1, &b);
-    kernel.set_arg(2, &c);
+    kernel.set_arg(0, &a).unwrap();
+    kernel.set_arg(1, &b).unwrap();
+    kernel.set_arg(2, &c).unwrap();
 
-    let event = queue.enqueue_async_kernel(&kernel, vec_a.len(), None, ());
+    let event = queue.enqueue_async_kernel(&kernel, vec_a.len(), None, ()).unwrap();
 
-    let vec_c: Vec<isize> = queue.get(&c, &event);
+    let vec_c: Vec<isize> = queue.get(&c, &event).unwrap();
 
     println!("  {}", string_from_slice(&vec_a[..]));
     println!("+ {}", string_from_slice(&vec_b[..]));
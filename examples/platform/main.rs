@@ -3,18 +3,23 @@ extern crate opencl;
 use opencl::hl;
 
 fn main() {
-    for platform in hl::get_platforms().iter() {
-        println!("Platform: {}", platform.name());
-        println!("Platform Version: {}", platform.version());
-        println!("Vendor:   {}", platform.vendor());
-        println!("Profile:  {}", platform.profile());
-        println!("Available extensions: {}", platform.extensions());
+    for platform in hl::get_platforms().unwrap().iter() {
+        println!("Platform: {}", platform.name().unwrap());
+        println!("Platform Version: {}", platform.version().unwrap());
+        println!("Vendor:   {}", platform.vendor().unwrap());
+        println!("Profile:  {}", platform.profile().unwrap());
+        println!("Available extensions: {}", platform.extensions().unwrap());
         println!("Available devices:");
-        for device in platform.get_devices().iter() {
-            println!("   Name: {}", device.name());
-            println!("   Type: {}", device.device_type());
-            println!("   Profile: {}", device.profile());
-            println!("   Compute Units: {}", device.compute_units());
+        for device in platform.get_devices().unwrap().iter() {
+            println!("   Name: {}", device.name().unwrap());
+            println!("   Type: {:?}", device.device_type().unwrap());
+            println!("   Profile: {}", device.profile().unwrap());
+            println!("   Compute Units: {}", device.compute_units().unwrap());
+            println!("   Global Memory: {} bytes", device.global_mem_size().unwrap());
+            println!("   Local Memory: {} bytes", device.local_mem_size().unwrap());
+            println!("   Max Work Group Size: {}", device.max_work_group_size().unwrap());
+            println!("   Max Work Item Sizes: {:?}", device.max_work_item_sizes().unwrap());
+            println!("   Driver Version: {}", device.driver_version().unwrap());
         }
     }
 }
@@ -9,6 +9,13 @@ use std::vec::Vec;
 use libc::{size_t, c_void};
 
 use hl::KernelArg;
+use error::Error;
+
+/// The extent of an array as `[width, height, depth]`. Elements are laid
+/// out row by row, then slice by slice; 2D arrays have a depth of 1.
+pub trait Dimensions {
+    fn dimensions(&self) -> [usize; 3];
+}
 
 pub struct Array3D<T> {
     width: usize,
@@ -59,6 +66,18 @@ impl<T: Clone> Array3D<T> {
     }
 }
 
+impl<T> Dimensions for Array3D<T> {
+    fn dimensions(&self) -> [usize; 3] {
+        [self.width, self.height, self.depth]
+    }
+}
+
+impl<T> Dimensions for Array3DCL<T> {
+    fn dimensions(&self) -> [usize; 3] {
+        [self.width, self.height, self.depth]
+    }
+}
+
 impl<T> Drop for Array3DCL<T> {
     fn drop(&mut self) {
         unsafe {
@@ -91,24 +110,23 @@ impl<'r, T> Put<Array3D<T>, Array3DCL<T>> for &'r Array3D<T>
 impl<T> Get<Array3DCL<T>, Array3D<T>> for Array3D<T>
 {
     fn get<F>(arr: &Array3DCL<T>, f: F)
-           -> Array3D<T>
-        where F: FnOnce(size_t, *mut c_void, size_t)
+           -> Result<Array3D<T>, Error>
+        where F: FnOnce(size_t, *mut c_void, size_t) -> Result<(), Error>
     {
-        let mut v: Vec<T> = Vec::with_capacity(arr.len());
+        let len = try!(arr.len());
+        let mut v: Vec<T> = Vec::with_capacity(len);
+        try!(f(0, v.as_mut_ptr() as *mut c_void, (len * mem::size_of::<T>()) as size_t));
+        // The elements have been read in full.
         unsafe {
-            v.set_len(arr.len());
+            v.set_len(len);
         }
 
-        let p = v.as_mut_ptr();
-        let len = v.len();
-        f(0, p as *mut c_void, (len * mem::size_of::<T>()) as size_t);
-
-        Array3D {
+        Ok(Array3D {
             width: arr.width,
             height: arr.height,
             depth: arr.depth,
             dat: v,
-        }
+        })
     }
 }
 
@@ -137,8 +155,8 @@ impl<T> Buffer<T> for Array3DCL<T> {
         &self.buf as *const cl_mem
     }
 
-    fn len(&self) -> usize {
-        self.width * self.height * self.depth
+    fn len(&self) -> Result<usize, Error> {
+        Ok(self.width * self.height * self.depth)
     }
 }
 
@@ -189,6 +207,18 @@ impl<T: Clone> Array2D<T> {
     }
 }
 
+impl<T> Dimensions for Array2D<T> {
+    fn dimensions(&self) -> [usize; 3] {
+        [self.width, self.height, 1]
+    }
+}
+
+impl<T> Dimensions for Array2DCL<T> {
+    fn dimensions(&self) -> [usize; 3] {
+        [self.width, self.height, 1]
+    }
+}
+
 impl<T> Drop for Array2DCL<T> {
     fn drop(&mut self) {
         unsafe {
@@ -219,23 +249,22 @@ impl<'r, T> Put<Array2D<T>, Array2DCL<T>> for &'r Array2D<T>
 impl<T> Get<Array2DCL<T>, Array2D<T>> for Array2D<T>
 {
     fn get<F>(arr: &Array2DCL<T>, f: F)
-           -> Array2D<T>
-        where F: FnOnce(size_t, *mut c_void, size_t)
+           -> Result<Array2D<T>, Error>
+        where F: FnOnce(size_t, *mut c_void, size_t) -> Result<(), Error>
     {
-        let mut v: Vec<T> = Vec::with_capacity(arr.len());
+        let len = try!(arr.len());
+        let mut v: Vec<T> = Vec::with_capacity(len);
+        try!(f(0, v.as_mut_ptr() as *mut c_void, (len * mem::size_of::<T>()) as size_t));
+        // The elements have been read in full.
         unsafe {
-            v.set_len(arr.len())
+            v.set_len(len);
         }
 
-        let p = v.as_mut_ptr();
-        let len = v.len();
-        f(0, p as *mut c_void, (len * mem::size_of::<T>()) as size_t);
-
-        Array2D {
+        Ok(Array2D {
             width: arr.width,
             height: arr.height,
             dat: v
-        }
+        })
     }
 }
 
@@ -264,8 +293,8 @@ impl<T> Buffer<T> for Array2DCL<T> {
         &self.buf as *const cl_mem
     }
 
-    fn len(&self) -> usize {
-        self.width * self.height
+    fn len(&self) -> Result<usize, Error> {
+        Ok(self.width * self.height)
     }
 }
 
@@ -39,6 +39,8 @@ pub type cl_device_fp_config            = cl_bitfield;
 pub type cl_device_mem_cache_type       = cl_uint;
 pub type cl_device_local_mem_type       = cl_uint;
 pub type cl_device_exec_capabilities    = cl_bitfield;
+pub type cl_device_partition_property   = libc::intptr_t;
+pub type cl_device_affinity_domain      = cl_bitfield;
 pub type cl_command_queue_properties    = cl_bitfield;
 
 pub type cl_context_properties          = libc::intptr_t;
@@ -76,7 +78,7 @@ pub struct cl_buffer_region {
 
 
 /// OpenCL error codes.
-#[derive(PartialEq, Debug)]
+#[derive(Copy, Clone, PartialEq, Debug)]
 #[repr()]
 pub enum CLStatus {
     CL_SUCCESS = 0,
@@ -158,6 +160,7 @@ pub static CL_DEVICE_TYPE_DEFAULT:                       cl_bitfield = 1 << 0;
 pub static CL_DEVICE_TYPE_CPU:                           cl_bitfield = 1 << 1;
 pub static CL_DEVICE_TYPE_GPU:                           cl_bitfield = 1 << 2;
 pub static CL_DEVICE_TYPE_ACCELERATOR:                   cl_bitfield = 1 << 3;
+pub static CL_DEVICE_TYPE_CUSTOM:                        cl_bitfield = 1 << 4;
 pub static CL_DEVICE_TYPE_ALL:                           cl_bitfield = 0xFFFFFFFF;
 
 /* cl_device_info */
@@ -224,6 +227,28 @@ pub static CL_DEVICE_NATIVE_VECTOR_WIDTH_DOUBLE:         cl_uint = 0x103B;
 pub static CL_DEVICE_NATIVE_VECTOR_WIDTH_HALF:           cl_uint = 0x103C;
 pub static CL_DEVICE_OPENCL_C_VERSION:                   cl_uint = 0x103D;
 
+/* cl_device_info - OpenCL 1.2 */
+pub static CL_DEVICE_PARENT_DEVICE:                      cl_uint = 0x1042;
+pub static CL_DEVICE_PARTITION_MAX_SUB_DEVICES:          cl_uint = 0x1043;
+pub static CL_DEVICE_PARTITION_PROPERTIES:               cl_uint = 0x1044;
+pub static CL_DEVICE_PARTITION_AFFINITY_DOMAIN:          cl_uint = 0x1045;
+pub static CL_DEVICE_PARTITION_TYPE:                     cl_uint = 0x1046;
+pub static CL_DEVICE_REFERENCE_COUNT:                    cl_uint = 0x1047;
+
+/* cl_device_partition_property - OpenCL 1.2 */
+pub static CL_DEVICE_PARTITION_EQUALLY:                  cl_device_partition_property = 0x1086;
+pub static CL_DEVICE_PARTITION_BY_COUNTS:                cl_device_partition_property = 0x1087;
+pub static CL_DEVICE_PARTITION_BY_COUNTS_LIST_END:       cl_device_partition_property = 0x0;
+pub static CL_DEVICE_PARTITION_BY_AFFINITY_DOMAIN:       cl_device_partition_property = 0x1088;
+
+/* cl_device_affinity_domain - bitfield - OpenCL 1.2 */
+pub static CL_DEVICE_AFFINITY_DOMAIN_NUMA:               cl_bitfield = 1 << 0;
+pub static CL_DEVICE_AFFINITY_DOMAIN_L4_CACHE:           cl_bitfield = 1 << 1;
+pub static CL_DEVICE_AFFINITY_DOMAIN_L3_CACHE:           cl_bitfield = 1 << 2;
+pub static CL_DEVICE_AFFINITY_DOMAIN_L2_CACHE:           cl_bitfield = 1 << 3;
+pub static CL_DEVICE_AFFINITY_DOMAIN_L1_CACHE:           cl_bitfield = 1 << 4;
+pub static CL_DEVICE_AFFINITY_DOMAIN_NEXT_PARTITIONABLE: cl_bitfield = 1 << 5;
+
 /* cl_device_fp_config - bitfield */
 pub static CL_FP_DENORM:                                 cl_bitfield = 1 << 0;
 pub static CL_FP_INF_NAN:                                cl_bitfield = 1 << 1;
@@ -416,6 +441,12 @@ pub static CL_COMMAND_WRITE_BUFFER_RECT:                 cl_uint = 0x1202;
 pub static CL_COMMAND_COPY_BUFFER_RECT:                  cl_uint = 0x1203;
 pub static CL_COMMAND_USER:                              cl_uint = 0x1204;
 
+/* cl_command_type - OpenCL 1.2 */
+pub static CL_COMMAND_BARRIER:                           cl_uint = 0x1205;
+pub static CL_COMMAND_MIGRATE_MEM_OBJECTS:               cl_uint = 0x1206;
+pub static CL_COMMAND_FILL_BUFFER:                       cl_uint = 0x1207;
+pub static CL_COMMAND_FILL_IMAGE:                        cl_uint = 0x1208;
+
 /* command execution status */
 pub static CL_COMPLETE:                                  cl_uint = 0x0;
 pub static CL_RUNNING:                                   cl_uint = 0x1;
@@ -468,9 +499,9 @@ pub mod ll {
                        pfn_notify: extern fn (*const libc::c_char, *const libc::c_void, libc::size_t, *mut libc::c_void),
                        user_data: *mut libc::c_void,
                        errcode_ret: *mut cl_int) -> cl_context;
-    pub fn clCreateContextFromType(properties: *mut cl_context_properties,
+    pub fn clCreateContextFromType(properties: *const cl_context_properties,
                                device_type: cl_device_type,
-                               pfn_notify: extern fn (*mut libc::c_char, *mut libc::c_void, libc::size_t, *mut libc::c_void),
+                               pfn_notify: extern fn (*const libc::c_char, *const libc::c_void, libc::size_t, *mut libc::c_void),
                                user_data: *mut libc::c_void,
                                errcode_ret: *mut cl_int) -> cl_context;
     pub fn clRetainContext(context: cl_context) -> cl_int;
@@ -580,7 +611,7 @@ pub mod ll {
                       num_devices: cl_uint,
                       device_list: *const cl_device_id,
                       options: *const libc::c_char,
-                      pfn_notify: extern fn (cl_program, *mut libc::c_void),
+                      pfn_notify: Option<extern fn (cl_program, *mut libc::c_void)>,
                       user_data: *mut libc::c_void) -> cl_int;
     pub fn clUnloadCompiler() -> cl_int;
     pub fn clGetProgramInfo(program: cl_program,
@@ -664,9 +695,9 @@ pub mod ll {
     pub fn clEnqueueReadBufferRect(command_queue: cl_command_queue,
                                buffer: cl_mem,
                                blocking_read: cl_bool,
-                               buffer_origin: *mut libc::size_t,
-                               host_origin: *mut libc::size_t,
-                               region: *mut libc::size_t,
+                               buffer_origin: *const libc::size_t,
+                               host_origin: *const libc::size_t,
+                               region: *const libc::size_t,
                                buffer_row_pitch: libc::size_t,
                                buffer_slice_pitch: libc::size_t,
                                host_row_pitch: libc::size_t,
@@ -685,15 +716,16 @@ pub mod ll {
                             event_wait_list: *const cl_event,
                             event: *mut cl_event) -> cl_int;
     pub fn clEnqueueWriteBufferRect(command_queue: cl_command_queue,
+                                buffer: cl_mem,
                                 blocking_write: cl_bool,
-                                buffer_origin: *mut libc::size_t,
-                                host_origin: *mut libc::size_t,
-                                region: *mut libc::size_t,
+                                buffer_origin: *const libc::size_t,
+                                host_origin: *const libc::size_t,
+                                region: *const libc::size_t,
                                 buffer_row_pitch: libc::size_t,
                                 buffer_slice_pitch: libc::size_t,
                                 host_row_pitch: libc::size_t,
                                 host_slice_pitch: libc::size_t,
-                                ptr: *mut libc::c_void,
+                                ptr: *const libc::c_void,
                                 num_events_in_wait_list: cl_uint,
                                 event_wait_list: *const cl_event,
                                 event: *mut cl_event) -> cl_int;
@@ -709,9 +741,9 @@ pub mod ll {
     pub fn clEnqueueCopyBufferRect(command_queue: cl_command_queue,
                                src_buffer: cl_mem,
                                dst_buffer: cl_mem,
-                               src_origin: *mut libc::size_t,
-                               dst_origin: *mut libc::size_t,
-                               region: *mut libc::size_t,
+                               src_origin: *const libc::size_t,
+                               dst_origin: *const libc::size_t,
+                               region: *const libc::size_t,
                                src_row_pitch: libc::size_t,
                                src_slice_pitch: libc::size_t,
                                dst_row_pitch: libc::size_t,
@@ -719,6 +751,7 @@ pub mod ll {
                                num_events_in_wait_list: cl_uint,
                                event_wait_list: *const cl_event,
                                event: *mut cl_event) -> cl_int;
+
     pub fn clEnqueueReadImage(command_queue: cl_command_queue,
                           image: cl_mem,
                           blocking_read: cl_bool,
@@ -777,7 +810,7 @@ pub mod ll {
                           num_events_in_wait_list: cl_uint,
                           event_wait_list: *const cl_event,
                           event: *mut cl_event,
-                          errorcode_ret: *mut cl_int);
+                          errorcode_ret: *mut cl_int) -> *mut libc::c_void;
     pub fn clEnqueueMapImage(command_queue: cl_command_queue,
                          image: cl_mem,
                          blocking_map: cl_bool,
@@ -824,7 +857,7 @@ pub mod ll {
                        event: *mut cl_event) -> cl_int;
     pub fn clEnqueueWaitForEvents(command_queue: cl_command_queue,
                               num_events: cl_uint,
-                              event_list: *mut cl_event) -> cl_int;
+                              event_list: *const cl_event) -> cl_int;
     pub fn clEnqueueBarrier(command_queue: cl_command_queue) -> cl_int;
 
     /* Extension function access
@@ -837,3 +870,101 @@ pub mod ll {
     pub fn clGetExtensionFunctionAddress(func_name: *const libc::c_char) -> *mut libc::c_void;
   }
 }
+
+/// OpenCL 1.2 entry points. They are looked up in the loaded OpenCL library
+/// instead of being linked, so that the crate still loads against a 1.1
+/// `libOpenCL`; only call them after checking the platform's version.
+#[allow(non_snake_case)]
+pub mod cl_1_2 {
+    use libc;
+    use std::ffi::CString;
+    use std::mem;
+    use std::sync::Once;
+
+    use cl::*;
+
+    #[cfg_attr(target_os = "linux", link(name = "dl"))]
+    #[cfg(unix)]
+    extern {
+        fn dlsym(handle: *mut libc::c_void, symbol: *const libc::c_char) -> *mut libc::c_void;
+    }
+
+    #[cfg(target_os = "macos")]
+    const RTLD_DEFAULT: isize = -2;
+    #[cfg(all(unix, not(target_os = "macos")))]
+    const RTLD_DEFAULT: isize = 0;
+
+    #[cfg(unix)]
+    fn symbol(name: &str) -> Result<*mut libc::c_void, String> {
+        let c_name = CString::new(name).unwrap();
+        let ptr = unsafe { dlsym(RTLD_DEFAULT as *mut libc::c_void, c_name.as_ptr()) };
+        if ptr.is_null() {
+            Err(format!("the OpenCL library does not export {}", name))
+        } else {
+            Ok(ptr)
+        }
+    }
+
+    #[cfg(not(unix))]
+    fn symbol(name: &str) -> Result<*mut libc::c_void, String> {
+        Err(format!("cannot look up {} on this target", name))
+    }
+
+    /// Looks `$lookup` up the first time it is evaluated and returns a
+    /// clone of the same result from then on.
+    macro_rules! cached {
+        ($t:ty, $lookup:expr) => ({
+            static INIT: Once = Once::new();
+            static mut VALUE: *const $t = 0 as *const $t;
+            unsafe {
+                INIT.call_once(|| VALUE = Box::into_raw(Box::new($lookup)));
+                (*VALUE).clone()
+            }
+        })
+    }
+
+    /// The entry points for partitioning devices.
+    #[derive(Copy, Clone)]
+    pub struct DeviceFission {
+        pub clCreateSubDevices: extern fn (in_device: cl_device_id,
+                                           properties: *const cl_device_partition_property,
+                                           num_devices: cl_uint,
+                                           out_devices: *mut cl_device_id,
+                                           num_devices_ret: *mut cl_uint) -> cl_int,
+        pub clRetainDevice: extern fn (device: cl_device_id) -> cl_int,
+        pub clReleaseDevice: extern fn (device: cl_device_id) -> cl_int,
+    }
+
+    pub type clEnqueueFillBuffer = extern fn (command_queue: cl_command_queue,
+                                              buffer: cl_mem,
+                                              pattern: *const libc::c_void,
+                                              pattern_size: libc::size_t,
+                                              offset: libc::size_t,
+                                              size: libc::size_t,
+                                              num_events_in_wait_list: cl_uint,
+                                              event_wait_list: *const cl_event,
+                                              event: *mut cl_event) -> cl_int;
+
+    // Each loader below looks up only the symbols it returns, the first time
+    // it is called, so a library missing one entry point still provides the
+    // others. Call them only after checking that the platform implements 1.2.
+
+    pub fn device_fission() -> Result<DeviceFission, String> {
+        cached!(Result<DeviceFission, String>, lookup_device_fission())
+    }
+
+    fn lookup_device_fission() -> Result<DeviceFission, String> {
+        unsafe {
+            Ok(DeviceFission {
+                clCreateSubDevices: mem::transmute(try!(symbol("clCreateSubDevices"))),
+                clRetainDevice: mem::transmute(try!(symbol("clRetainDevice"))),
+                clReleaseDevice: mem::transmute(try!(symbol("clReleaseDevice"))),
+            })
+        }
+    }
+
+    pub fn enqueue_fill_buffer() -> Result<clEnqueueFillBuffer, String> {
+        cached!(Result<clEnqueueFillBuffer, String>,
+                symbol("clEnqueueFillBuffer").map(|f| unsafe { mem::transmute(f) }))
+    }
+}
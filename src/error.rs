@@ -1,62 +1,74 @@
 //! Error handling utilities.
 
+use std::error;
+use std::fmt;
+
 use cl::{CLStatus, cl_int};
 use cl::CLStatus::CL_SUCCESS;
 
+/// Converts a raw status code into a `CLStatus`, if it is one we know about.
+pub fn try_convert(status_code: cl_int) -> Option<CLStatus> {
+    let status = match status_code {
+    0 => CLStatus::CL_SUCCESS,
+    -1 => CLStatus::CL_DEVICE_NOT_FOUND,
+    -2 => CLStatus::CL_DEVICE_NOT_AVAILABLE,
+    -3 => CLStatus::CL_COMPILER_NOT_AVAILABLE,
+    -4 => CLStatus::CL_MEM_OBJECT_ALLOCATION_FAILURE,
+    -5 => CLStatus::CL_OUT_OF_RESOURCES,
+    -6 => CLStatus::CL_OUT_OF_HOST_MEMORY,
+    -7 => CLStatus::CL_PROFILING_INFO_NOT_AVAILABLE,
+    -8 => CLStatus::CL_MEM_COPY_OVERLAP,
+    -9 => CLStatus::CL_IMAGE_FORMAT_MISMATCH,
+    -10 => CLStatus::CL_IMAGE_FORMAT_NOT_SUPPORTED,
+    -11 => CLStatus::CL_BUILD_PROGRAM_FAILURE,
+    -12 => CLStatus::CL_MAP_FAILURE,
+    -13 => CLStatus::CL_MISALIGNED_SUB_BUFFER_OFFSET,
+    -14 => CLStatus::CL_EXEC_STATUS_ERROR_FOR_EVENTS_IN_WAIT_LIST,
+    -30 => CLStatus::CL_INVALID_VALUE,
+    -31 => CLStatus::CL_INVALID_DEVICE_TYPE,
+    -32 => CLStatus::CL_INVALID_PLATFORM,
+    -33 => CLStatus::CL_INVALID_DEVICE,
+    -34 => CLStatus::CL_INVALID_CONTEXT,
+    -35 => CLStatus::CL_INVALID_QUEUE_PROPERTIES,
+    -36 => CLStatus::CL_INVALID_COMMAND_QUEUE,
+    -37 => CLStatus::CL_INVALID_HOST_PTR,
+    -38 => CLStatus::CL_INVALID_MEM_OBJECT,
+    -39 => CLStatus::CL_INVALID_IMAGE_FORMAT_DESCRIPTOR,
+    -40 => CLStatus::CL_INVALID_IMAGE_SIZE,
+    -41 => CLStatus::CL_INVALID_SAMPLER,
+    -42 => CLStatus::CL_INVALID_BINARY,
+    -43 => CLStatus::CL_INVALID_BUILD_OPTIONS,
+    -44 => CLStatus::CL_INVALID_PROGRAM,
+    -45 => CLStatus::CL_INVALID_PROGRAM_EXECUTABLE,
+    -46 => CLStatus::CL_INVALID_KERNEL_NAME,
+    -47 => CLStatus::CL_INVALID_KERNEL_DEFINITION,
+    -48 => CLStatus::CL_INVALID_KERNEL,
+    -49 => CLStatus::CL_INVALID_ARG_INDEX,
+    -50 => CLStatus::CL_INVALID_ARG_VALUE,
+    -51 => CLStatus::CL_INVALID_ARG_SIZE,
+    -52 => CLStatus::CL_INVALID_KERNEL_ARGS,
+    -53 => CLStatus::CL_INVALID_WORK_DIMENSION,
+    -54 => CLStatus::CL_INVALID_WORK_GROUP_SIZE,
+    -55 => CLStatus::CL_INVALID_WORK_ITEM_SIZE,
+    -56 => CLStatus::CL_INVALID_GLOBAL_OFFSET,
+    -57 => CLStatus::CL_INVALID_EVENT_WAIT_LIST,
+    -58 => CLStatus::CL_INVALID_EVENT,
+    -59 => CLStatus::CL_INVALID_OPERATION,
+    -60 => CLStatus::CL_INVALID_GL_OBJECT,
+    -61 => CLStatus::CL_INVALID_BUFFER_SIZE,
+    -62 => CLStatus::CL_INVALID_MIP_LEVEL,
+    -63 => CLStatus::CL_INVALID_GLOBAL_WORK_SIZE,
+    -64 => CLStatus::CL_INVALID_PROPERTY,
+    -1001 => CLStatus::CL_PLATFORM_NOT_FOUND_KHR,
+        _ => return None
+    };
+    Some(status)
+}
+
 fn error_str(status_code: cl_int) -> String {
-    match status_code {
-    0 => CLStatus::CL_SUCCESS.to_string(),
-    -1 => CLStatus::CL_DEVICE_NOT_FOUND.to_string(),
-    -2 => CLStatus::CL_DEVICE_NOT_AVAILABLE.to_string(),
-    -3 => CLStatus::CL_COMPILER_NOT_AVAILABLE.to_string(),
-    -4 => CLStatus::CL_MEM_OBJECT_ALLOCATION_FAILURE.to_string(),
-    -5 => CLStatus::CL_OUT_OF_RESOURCES.to_string(),
-    -6 => CLStatus::CL_OUT_OF_HOST_MEMORY.to_string(),
-    -7 => CLStatus::CL_PROFILING_INFO_NOT_AVAILABLE.to_string(),
-    -8 => CLStatus::CL_MEM_COPY_OVERLAP.to_string(),
-    -9 => CLStatus::CL_IMAGE_FORMAT_MISMATCH.to_string(),
-    -10 => CLStatus::CL_IMAGE_FORMAT_NOT_SUPPORTED.to_string(),
-    -11 => CLStatus::CL_BUILD_PROGRAM_FAILURE.to_string(),
-    -12 => CLStatus::CL_MAP_FAILURE.to_string(),
-    -13 => CLStatus::CL_MISALIGNED_SUB_BUFFER_OFFSET.to_string(),
-    -14 => CLStatus::CL_EXEC_STATUS_ERROR_FOR_EVENTS_IN_WAIT_LIST.to_string(),
-    -30 => CLStatus::CL_INVALID_VALUE.to_string(),
-    -31 => CLStatus::CL_INVALID_DEVICE_TYPE.to_string(),
-    -32 => CLStatus::CL_INVALID_PLATFORM.to_string(),
-    -33 => CLStatus::CL_INVALID_DEVICE.to_string(),
-    -34 => CLStatus::CL_INVALID_CONTEXT.to_string(),
-    -35 => CLStatus::CL_INVALID_QUEUE_PROPERTIES.to_string(),
-    -36 => CLStatus::CL_INVALID_COMMAND_QUEUE.to_string(),
-    -37 => CLStatus::CL_INVALID_HOST_PTR.to_string(),
-    -38 => CLStatus::CL_INVALID_MEM_OBJECT.to_string(),
-    -39 => CLStatus::CL_INVALID_IMAGE_FORMAT_DESCRIPTOR.to_string(),
-    -40 => CLStatus::CL_INVALID_IMAGE_SIZE.to_string(),
-    -41 => CLStatus::CL_INVALID_SAMPLER.to_string(),
-    -42 => CLStatus::CL_INVALID_BINARY.to_string(),
-    -43 => CLStatus::CL_INVALID_BUILD_OPTIONS.to_string(),
-    -44 => CLStatus::CL_INVALID_PROGRAM.to_string(),
-    -45 => CLStatus::CL_INVALID_PROGRAM_EXECUTABLE.to_string(),
-    -46 => CLStatus::CL_INVALID_KERNEL_NAME.to_string(),
-    -47 => CLStatus::CL_INVALID_KERNEL_DEFINITION.to_string(),
-    -48 => CLStatus::CL_INVALID_KERNEL.to_string(),
-    -49 => CLStatus::CL_INVALID_ARG_INDEX.to_string(),
-    -50 => CLStatus::CL_INVALID_ARG_VALUE.to_string(),
-    -51 => CLStatus::CL_INVALID_ARG_SIZE.to_string(),
-    -52 => CLStatus::CL_INVALID_KERNEL_ARGS.to_string(),
-    -53 => CLStatus::CL_INVALID_WORK_DIMENSION.to_string(),
-    -54 => CLStatus::CL_INVALID_WORK_GROUP_SIZE.to_string(),
-    -55 => CLStatus::CL_INVALID_WORK_ITEM_SIZE.to_string(),
-    -56 => CLStatus::CL_INVALID_GLOBAL_OFFSET.to_string(),
-    -57 => CLStatus::CL_INVALID_EVENT_WAIT_LIST.to_string(),
-    -58 => CLStatus::CL_INVALID_EVENT.to_string(),
-    -59 => CLStatus::CL_INVALID_OPERATION.to_string(),
-    -60 => CLStatus::CL_INVALID_GL_OBJECT.to_string(),
-    -61 => CLStatus::CL_INVALID_BUFFER_SIZE.to_string(),
-    -62 => CLStatus::CL_INVALID_MIP_LEVEL.to_string(),
-    -63 => CLStatus::CL_INVALID_GLOBAL_WORK_SIZE.to_string(),
-    -64 => CLStatus::CL_INVALID_PROPERTY.to_string(),
-    -1001 => CLStatus::CL_PLATFORM_NOT_FOUND_KHR.to_string(),
-        _ => format!("Unknown Error: {}", status_code)
+    match try_convert(status_code) {
+        Some(status) => status.to_string(),
+        None => format!("Unknown Error: {}", status_code)
     }
 }
 
@@ -65,3 +77,63 @@ pub fn check(status: cl_int, message: &str) {
         panic!("{} ({})", message, error_str(status))
     }
 }
+
+/// Turns a status code into a `Result`, remembering which OpenCL entry
+/// point produced it.
+pub fn check_status(status: cl_int, api: &'static str, message: &str) -> Result<(), Error> {
+    if status != CL_SUCCESS as cl_int {
+        Err(Error::new(status, api, message))
+    } else {
+        Ok(())
+    }
+}
+
+/// An error reported by the OpenCL runtime, or detected by the high-level
+/// API before calling into it.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Error {
+    status: cl_int,
+    api: &'static str,
+    message: String,
+}
+
+impl Error {
+    pub fn new(status: cl_int, api: &'static str, message: &str) -> Error {
+        Error {
+            status: status,
+            api: api,
+            message: message.to_string(),
+        }
+    }
+
+    /// The status code, as an OpenCL error code if it is a known one.
+    pub fn status(&self) -> Option<CLStatus> {
+        try_convert(self.status)
+    }
+
+    /// The raw status code.
+    pub fn status_code(&self) -> cl_int {
+        self.status
+    }
+
+    /// Name of the OpenCL function that failed.
+    pub fn api(&self) -> &'static str {
+        self.api
+    }
+
+    pub fn message(&self) -> &str {
+        &self.message
+    }
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{} ({}: {})", self.message, self.api, error_str(self.status))
+    }
+}
+
+impl error::Error for Error {
+    fn description(&self) -> &str {
+        &self.message
+    }
+}
@@ -6,10 +6,13 @@
 /// All of the extensions defined for OpenCL 1.1, from
 /// [`cl_ext.h`](https://www.khronos.org/registry/cl/api/1.1/cl_ext.h).
 
+use cl::cl_platform_id;
+use hl;
+
 // Macro to define a struct-style extension pointer loader.
 // Defines a (`Copy`, `Sync`) `struct Functions` that has extension function pointers as members (and methods,
 // for convenience).
-// Call `$ext::load()` to get an Option<$ext::Functions, String>.  (It's safe to call extension function pointers in other threads, right?)
+// Call `$ext::load(&platform)` to get a Result<$ext::Functions, String>.  (It's safe to call extension function pointers in other threads, right?)
 macro_rules! cl_extension_loader {
     (
         $ext_name:expr;
@@ -22,8 +25,7 @@ macro_rules! cl_extension_loader {
             $( #[inline(always)] unsafe fn $function (&self, $($arg:$arg_type),*) -> $ret { (self.$function)($($arg),*) } )*
         }
 
-        pub fn load(platform: cl_platform_id) -> Result<Functions, String> {
-            use hl;
+        pub fn load(platform: &::hl::Platform) -> Result<Functions, String> {
             use std::mem;
             use std::ptr;
             use std::ffi::CString;
@@ -32,20 +34,10 @@ macro_rules! cl_extension_loader {
             // Read in the available extensions
             // We have to do this, since loading function pointers for an
             // unavailable extension can return non-NULL.
-            // TODO read in extensions lazily and store them in a global HashSet?
-            let available = unsafe {
-                let hl_platform = hl::Platform::from_platform_id(platform);
-                let available = hl_platform.extensions().contains($ext_name);
-                mem::forget(hl_platform);
-                available
-            };
+            let available = try!(super::extension_available(platform, $ext_name));
             if !available {
-                let platform_name;
-                unsafe {
-                    let hl_platform = hl::Platform::from_platform_id(platform);
-                    platform_name = hl_platform.name();
-                    mem::forget(hl_platform);
-                }
+                let platform_name = platform.name()
+                    .unwrap_or_else(|_| format!("{:?}", platform.get_id()));
                 return Err(format!("extension {} unavailable for platform {}", $ext_name, platform_name));
             }
             // Return a struct with all functions loaded
@@ -53,6 +45,23 @@ macro_rules! cl_extension_loader {
         }
     )
 }
+
+/// Whether the platform, or any of its devices, lists exactly the extension
+/// `name`. Device-only extensions such as `cl_khr_fp64` are often missing from
+/// the platform's own list.
+fn extension_available(platform: &hl::Platform, name: &str) -> Result<bool, String> {
+    if try!(platform.supports(name).map_err(|e| e.to_string())) {
+        return Ok(true);
+    }
+    let devices = try!(platform.get_devices().map_err(|e| e.to_string()));
+    for device in devices.iter() {
+        if try!(device.supports(name).map_err(|e| e.to_string())) {
+            return Ok(true);
+        }
+    }
+    Ok(false)
+}
+
 // We only need these helper macros so we can special-case for unit structs
 // (Since writing `struct name {}` is a failing error for some reason)
 // Whatever functions we're calling aren't necessarily thread-safe, but since this is a low-level
@@ -80,13 +89,8 @@ macro_rules! ext_struct_literal {
                 // reliable.
                 let fn_ptr = unsafe { clGetExtensionFunctionAddress(fn_name.as_ptr()) };
                 if fn_ptr == ptr::null_mut() {
-                    let platform_name;
-
-                    unsafe {
-                        let hl_platform = hl::Platform::from_platform_id($plat);
-                        platform_name = hl_platform.name();
-                        mem::forget(hl_platform);
-                    }
+                    let platform_name = $plat.name()
+                        .unwrap_or_else(|_| format!("{:?}", $plat.get_id()));
 
                     return Err(format!("extension {} apparently available for platform with id {}, but couldn't load function {}",
                                        $ext_name,
@@ -104,7 +108,7 @@ macro_rules! ext_struct_literal {
 
 pub mod cl_khr_fp64 {
     use cl::*;
-    static CL_DEVICE_DOUBLE_FP_CONFIG: cl_uint = 0x1032;
+    pub static CL_DEVICE_DOUBLE_FP_CONFIG: cl_uint = 0x1032;
     cl_extension_loader! {
         "cl_khr_fp64";
     }
@@ -114,7 +118,7 @@ pub mod cl_khr_fp16 {
     use cl::*;
     pub static CL_DEVICE_HALF_FP_CONFIG: cl_uint = 0x1033;
     cl_extension_loader! {
-        "cl_khr_pf16";
+        "cl_khr_fp16";
     }
 }
 
@@ -223,7 +227,7 @@ pub mod cl_ext_device_fission {
         "cl_ext_device_fission";
         extern fn clReleaseDeviceEXT(device: cl_device_id) -> cl_int,
         extern fn clRetainDeviceEXT(device: cl_device_id) -> cl_int,
-        extern fn clCreateSubDevicesExt(in_device: cl_device_id,
+        extern fn clCreateSubDevicesEXT(in_device: cl_device_id,
                                         properties: *const cl_device_partition_property_ext,
                                         num_entries: cl_uint,
                                         out_devices: *mut cl_device_id,
@@ -277,3 +281,26 @@ pub mod cl_qcom_ion_host_ptr {
         "cl_qcom_ion_host_ptr";
     }
 }
+
+/// OpenGL sharing, from [`cl_gl.h`](https://www.khronos.org/registry/cl/api/1.1/cl_gl.h).
+pub mod cl_khr_gl_sharing {
+    use libc;
+    use cl::*;
+    pub type cl_gl_context_info = cl_uint;
+    pub static CL_INVALID_GL_SHAREGROUP_REFERENCE_KHR:  cl_int = -1000;
+    pub static CL_CURRENT_DEVICE_FOR_GL_CONTEXT_KHR:    cl_gl_context_info = 0x2006;
+    pub static CL_DEVICES_FOR_GL_CONTEXT_KHR:           cl_gl_context_info = 0x2007;
+    pub static CL_GL_CONTEXT_KHR:                       cl_context_properties = 0x2008;
+    pub static CL_EGL_DISPLAY_KHR:                      cl_context_properties = 0x2009;
+    pub static CL_GLX_DISPLAY_KHR:                      cl_context_properties = 0x200A;
+    pub static CL_WGL_HDC_KHR:                          cl_context_properties = 0x200B;
+    pub static CL_CGL_SHAREGROUP_KHR:                   cl_context_properties = 0x200C;
+    cl_extension_loader! {
+        "cl_khr_gl_sharing";
+        extern fn clGetGLContextInfoKHR(properties: *const cl_context_properties,
+                                        param_name: cl_gl_context_info,
+                                        param_value_size: libc::size_t,
+                                        param_value: *mut libc::c_void,
+                                        param_value_size_ret: *mut libc::size_t) -> cl_int
+    }
+}
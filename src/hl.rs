@@ -1,30 +1,131 @@
 //! A higher level API.
 
 use libc;
-use std::ffi::CString;
+use std::cmp;
+use std::collections::{HashMap, HashSet};
+use std::error;
+use std::ffi::{CStr, CString};
+use std::fmt;
 use std::iter::repeat;
 use std::marker::PhantomData;
 use std::mem;
+use std::ops::{Deref, DerefMut, Range};
+use std::panic::{self, AssertUnwindSafe};
 use std::ptr;
+use std::slice;
 use std::string::String;
+use std::sync::{Arc, Mutex, MutexGuard, Once, Weak};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::thread;
+use std::time::{Duration, Instant};
 use std::vec::Vec;
 
 use cl;
 use cl::*;
 use cl::ll::*;
-use cl::CLStatus::CL_SUCCESS;
-use error::check;
+use cl::cl_1_2;
+use cl::CLStatus::{CL_SUCCESS, CL_DEVICE_NOT_FOUND, CL_INVALID_VALUE, CL_INVALID_BUILD_OPTIONS,
+                   CL_INVALID_OPERATION, CL_INVALID_DEVICE, CL_INVALID_QUEUE_PROPERTIES,
+                   CL_PROFILING_INFO_NOT_AVAILABLE, CL_INVALID_WORK_DIMENSION,
+                   CL_INVALID_GLOBAL_WORK_SIZE, CL_INVALID_WORK_ITEM_SIZE,
+                   CL_INVALID_WORK_GROUP_SIZE, CL_MEM_COPY_OVERLAP, CL_INVALID_EVENT};
+use error::{Error, check_status};
+use info::{get_info, get_info_vec, get_info_string};
+use ext::{cl_khr_fp16, cl_khr_fp64, cl_ext_device_fission};
+use ext::{cl_nv_device_attribute_query, cl_amd_device_attribute_query};
+use ext::cl_arm_printf;
+use ext::cl_ext_device_fission::cl_device_partition_property_ext;
 use mem::{Put, Get, Write, Read, Buffer, CLBuffer};
+use array::Dimensions;
 
-#[derive(Copy, Clone)]
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
 pub enum DeviceType {
-      CPU, GPU
+      CPU, GPU, Accelerator, Default, Custom, All
 }
 
 fn convert_device_type(device: DeviceType) -> cl_device_type {
     match device {
         DeviceType::CPU => CL_DEVICE_TYPE_CPU,
-        DeviceType::GPU => CL_DEVICE_TYPE_GPU | CL_DEVICE_TYPE_ACCELERATOR
+        DeviceType::GPU => CL_DEVICE_TYPE_GPU,
+        DeviceType::Accelerator => CL_DEVICE_TYPE_ACCELERATOR,
+        DeviceType::Default => CL_DEVICE_TYPE_DEFAULT,
+        DeviceType::Custom => CL_DEVICE_TYPE_CUSTOM,
+        DeviceType::All => CL_DEVICE_TYPE_ALL
+    }
+}
+
+/// Splits a `CL_DEVICE_TYPE` bitfield into the individual types it holds.
+fn decode_device_type(bits: cl_device_type) -> Vec<DeviceType> {
+    [DeviceType::Default, DeviceType::CPU, DeviceType::GPU,
+     DeviceType::Accelerator, DeviceType::Custom].iter()
+        .filter(|&&t| bits & convert_device_type(t) != 0)
+        .cloned()
+        .collect()
+}
+
+/// Splits a space-separated extension list into its names.
+fn parse_extensions(extensions: &str) -> HashSet<String> {
+    extensions.split_whitespace().map(|e| e.to_string()).collect()
+}
+
+/// An OpenCL version number.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct ClVersion {
+    pub major: u32,
+    pub minor: u32,
+}
+
+impl ClVersion {
+    pub fn new(major: u32, minor: u32) -> ClVersion {
+        ClVersion { major: major, minor: minor }
+    }
+
+    /// Parses a version string as reported by `CL_PLATFORM_VERSION`,
+    /// `CL_DEVICE_VERSION` ("OpenCL 1.2 pocl 1.8") or
+    /// `CL_DEVICE_OPENCL_C_VERSION` ("OpenCL C 1.2 ").
+    pub fn parse(version: &str) -> Option<ClVersion> {
+        let mut words = version.split_whitespace();
+        if words.next() != Some("OpenCL") {
+            return None;
+        }
+        let mut number = words.next();
+        if number == Some("C") {
+            number = words.next();
+        }
+        let mut parts = match number {
+            Some(number) => number.splitn(2, '.'),
+            None => return None
+        };
+        let major = parts.next().and_then(|n| n.parse().ok());
+        let minor = parts.next().and_then(|n| n.parse().ok());
+        match (major, minor) {
+            (Some(major), Some(minor)) => Some(ClVersion::new(major, minor)),
+            _ => None
+        }
+    }
+
+    fn parse_info(version: &str, api: &'static str) -> Result<ClVersion, Error> {
+        match ClVersion::parse(version) {
+            Some(v) => Ok(v),
+            None => Err(Error::new(CL_INVALID_VALUE as cl_int, api,
+                                   &format!("Malformed OpenCL version \"{}\"", version)))
+        }
+    }
+
+    fn require(&self, required: ClVersion, api: &'static str, what: &str) -> Result<(), Error> {
+        if *self >= required {
+            Ok(())
+        } else {
+            Err(Error::new(CL_INVALID_OPERATION as cl_int, api,
+                           &format!("{} requires OpenCL {}, but the {} only supports OpenCL {}",
+                                    api, required, what, self)))
+        }
+    }
+}
+
+impl fmt::Display for ClVersion {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}.{}", self.major, self.minor)
     }
 }
 
@@ -33,7 +134,7 @@ pub struct Platform {
 }
 
 impl Platform {
-    fn get_devices_internal(&self, dtype: cl_device_type) -> Vec<Device>
+    fn get_devices_internal(&self, dtype: cl_device_type) -> Result<Vec<Device>, Error>
     {
         unsafe
         {
@@ -41,23 +142,29 @@ impl Platform {
 
             info!("Looking for devices matching {}", dtype);
 
-            clGetDeviceIDs(self.id, dtype, 0, ptr::null_mut(),
-                           (&mut num_devices));
+            let status = clGetDeviceIDs(self.id, dtype, 0, ptr::null_mut(),
+                                        (&mut num_devices));
+            // Having no device of the requested type is not an error.
+            if status == CL_DEVICE_NOT_FOUND as cl_int {
+                return Ok(Vec::new());
+            }
+            try!(check_status(status, "clGetDeviceIDs", "Could not get device count"));
 
             let mut ids: Vec<cl_device_id> = repeat(0 as cl_device_id)
                 .take(num_devices as usize).collect();
-            clGetDeviceIDs(self.id, dtype, ids.len() as cl_uint,
-                           ids.as_mut_ptr(), (&mut num_devices));
-            ids.iter().map(|id| { Device {id: *id }}).collect()
+            let status = clGetDeviceIDs(self.id, dtype, ids.len() as cl_uint,
+                                        ids.as_mut_ptr(), (&mut num_devices));
+            try!(check_status(status, "clGetDeviceIDs", "Could not get devices"));
+            Ok(ids.iter().map(|id| { Device {id: *id }}).collect())
         }
     }
 
-    pub fn get_devices(&self) -> Vec<Device>
+    pub fn get_devices(&self) -> Result<Vec<Device>, Error>
     {
         self.get_devices_internal(CL_DEVICE_TYPE_ALL)
     }
 
-    pub fn get_devices_by_types(&self, types: &[DeviceType]) -> Vec<Device>
+    pub fn get_devices_by_types(&self, types: &[DeviceType]) -> Result<Vec<Device>, Error>
     {
         let mut dtype = 0;
         for &t in types.iter() {
@@ -67,61 +174,71 @@ impl Platform {
         self.get_devices_internal(dtype)
     }
 
-    fn profile_info(&self, name: cl_platform_info) -> String
+    fn profile_info(&self, name: cl_platform_info) -> Result<String, Error>
     {
-        unsafe {
-            let mut size = 0 as libc::size_t;
-
-            let status = clGetPlatformInfo(self.id,
-                              name,
-                              0,
-                              ptr::null_mut(),
-                              &mut size);
-            check(status, "Could not determine platform info string length");
-
-            let mut buf : Vec<u8>
-                = repeat(0u8).take(size as usize).collect();
-
-            let status = clGetPlatformInfo(self.id,
-                              name,
-                              size,
-                              buf.as_mut_ptr() as *mut libc::c_void,
-                              ptr::null_mut());
-            check(status, "Could not get platform info string");
-
-            String::from_utf8_unchecked(buf)
-        }
+        get_info_string("clGetPlatformInfo", name, |size, value, size_ret| unsafe {
+            clGetPlatformInfo(self.id, name, size, value, size_ret)
+        })
     }
 
     pub fn get_id(&self) -> cl_platform_id {
         self.id
     }
 
-    pub fn name(&self) -> String
+    pub fn name(&self) -> Result<String, Error>
     {
         self.profile_info(CL_PLATFORM_NAME)
     }
 
-    pub fn version(&self) -> String
+    pub fn version(&self) -> Result<String, Error>
     {
         self.profile_info(CL_PLATFORM_VERSION)
     }
 
-    pub fn profile(&self) -> String
+    /// The OpenCL version the platform implements.
+    pub fn cl_version(&self) -> Result<ClVersion, Error>
+    {
+        let version = try!(self.version());
+        ClVersion::parse_info(&version, "clGetPlatformInfo")
+    }
+
+    /// Fails with a descriptive error unless the platform implements at
+    /// least `required`, the version needed by the entry point `api`.
+    /// Entry points newer than OpenCL 1.1 are looked up at runtime (see
+    /// `cl::cl_1_2`), so this check also works against a 1.1 library.
+    pub fn require_version(&self, required: ClVersion, api: &'static str) -> Result<(), Error>
+    {
+        let version = try!(self.cl_version());
+        version.require(required, api, "platform")
+    }
+
+    pub fn profile(&self) -> Result<String, Error>
     {
         self.profile_info(CL_PLATFORM_PROFILE)
     }
 
-    pub fn vendor(&self) -> String
+    pub fn vendor(&self) -> Result<String, Error>
     {
         self.profile_info(CL_PLATFORM_VENDOR)
     }
 
-    pub fn extensions(&self) -> String
+    pub fn extensions(&self) -> Result<String, Error>
     {
         self.profile_info(CL_PLATFORM_EXTENSIONS)
     }
 
+    /// The platform's extensions, one name per entry.
+    pub fn extensions_set(&self) -> Result<HashSet<String>, Error>
+    {
+        self.extensions().map(|e| parse_extensions(&e))
+    }
+
+    /// Whether the platform lists exactly the extension `name`.
+    pub fn supports(&self, name: &str) -> Result<bool, Error>
+    {
+        self.extensions_set().map(|set| set.contains(name))
+    }
+
     pub unsafe fn from_platform_id(id: cl_platform_id) -> Platform {
         Platform { id: id }
     }
@@ -132,7 +249,7 @@ impl Platform {
 // will cause the implantation to return invalid status.
 static mut platforms_mutex: std::sync::StaticMutex = std::sync::MUTEX_INIT;
 
-pub fn get_platforms() -> Vec<Platform>
+pub fn get_platforms() -> Result<Vec<Platform>, Error>
 {
     let mut num_platforms = 0 as cl_uint;
 
@@ -142,8 +259,7 @@ pub fn get_platforms() -> Vec<Platform>
         let status = clGetPlatformIDs(0,
                                           ptr::null_mut(),
                                           (&mut num_platforms));
-        // unlock this before the check in case the check fails
-        check(status, "could not get platform count.");
+        try!(check_status(status, "clGetPlatformIDs", "could not get platform count."));
 
         let mut ids: Vec<cl_device_id> = repeat(0 as cl_device_id)
             .take(num_platforms as usize).collect();
@@ -151,348 +267,1806 @@ pub fn get_platforms() -> Vec<Platform>
         let status = clGetPlatformIDs(num_platforms,
                                       ids.as_mut_ptr(),
                                       (&mut num_platforms));
-        check(status, "could not get platforms.");
+        try!(check_status(status, "clGetPlatformIDs", "could not get platforms."));
 
         let _ = guard;
 
-        ids.iter().map(|id| { Platform { id: *id } }).collect()
+        Ok(ids.iter().map(|id| { Platform { id: *id } }).collect())
+    }
+}
+
+/// Receives the errors an implementation reports asynchronously for a
+/// context: a message and an implementation-specific binary blob. It is called
+/// from driver threads; a panic is logged and otherwise ignored.
+pub type ContextNotify = Fn(&str, &[u8]) + Send + Sync;
+
+/// Runs `f` for a callback made by the OpenCL implementation. A panic must
+/// not unwind into C, so it is caught and logged instead.
+fn guard_callback<F: FnOnce()>(callback: &str, f: F)
+{
+    if panic::catch_unwind(AssertUnwindSafe(f)).is_err() {
+        error!("A panic in {} was caught before it could unwind into OpenCL", callback);
+    }
+}
+
+/// `pfn_notify` for every context. Without a closure in `user_data` the
+/// message goes to the log.
+extern fn context_notify(errinfo: *const libc::c_char,
+                         private_info: *const libc::c_void,
+                         cb: libc::size_t,
+                         user_data: *mut libc::c_void)
+{
+    unsafe {
+        let message = if errinfo.is_null() {
+            "".into()
+        } else {
+            CStr::from_ptr(errinfo).to_string_lossy()
+        };
+        let info = if private_info.is_null() || cb == 0 {
+            &[][..]
+        } else {
+            slice::from_raw_parts(private_info as *const u8, cb as usize)
+        };
+
+        if user_data.is_null() {
+            error!("OpenCL context error: {}", message);
+        } else {
+            let notify = &*(user_data as *const Box<ContextNotify>);
+            guard_callback("a context notification", || notify(&message, info));
+        }
     }
 }
 
-pub fn create_context_with_properties(dev: &[Device], prop: &[cl_context_properties]) -> Context
+/// Creates a context from a raw, zero-terminated property list. An empty
+/// list stands for no properties. Errors reported by the implementation are
+/// logged.
+pub fn create_context_with_properties(dev: &[Device], prop: &[cl_context_properties]) -> Result<Context, Error>
+{
+    create_context_notify(dev, prop, None)
+}
+
+fn create_context_notify(dev: &[Device], prop: &[cl_context_properties],
+                         notify: Option<Box<ContextNotify>>) -> Result<Context, Error>
 {
+    if dev.is_empty() {
+        return Err(Error::new(CL_INVALID_VALUE as cl_int, "clCreateContext",
+                              "A context needs at least one device"));
+    }
+
     unsafe
     {
-        // TODO: Support for multiple devices
         let mut errcode = 0;
         let dev: Vec<cl_device_id> = dev.iter().map(|dev| dev.id).collect();
+        let prop = if prop.is_empty() { ptr::null() } else { prop.as_ptr() };
+        let state = ContextState::new(notify);
+        let user_data = match state.notify {
+            Some(ref notify) => notify as *const Box<ContextNotify> as *mut libc::c_void,
+            None => ptr::null_mut()
+        };
 
-        // TODO: Proper error messages
-        let ctx = clCreateContext(&prop[0],
+        let ctx = clCreateContext(prop,
                                   dev.len() as u32,
-                                  &dev[0],
-                                  mem::transmute(ptr::null::<fn()>()),
-                                  ptr::null_mut(),
+                                  dev.as_ptr(),
+                                  context_notify,
+                                  user_data,
                                   &mut errcode);
 
-        check(errcode, "Failed to create opencl context!");
+        try!(check_status(errcode, "clCreateContext", "Failed to create opencl context!"));
 
-        Context { ctx: ctx }
+        ContextState::register(ctx, &state);
+        Ok(Context { ctx: ctx, state: state })
     }
 }
 
-#[derive(Copy, Clone)]
-pub struct Device {
-    id: cl_device_id
+/// Receives the output of `printf` calls in kernels on ARM devices
+/// (`cl_arm_printf`). `user_data` is internal to the context and must be
+/// ignored.
+pub type ArmPrintfCallback = extern fn(buffer: *const libc::c_char,
+                                       len: libc::size_t,
+                                       complete: libc::size_t,
+                                       user_data: *mut libc::c_void);
+
+/// A window-system handle an OpenGL context is shared through
+/// (`cl_khr_gl_sharing`).
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum GlSharing {
+    /// The `GLXDisplay`, `EGLDisplay` or `HDC` identifies the OpenGL
+    /// context's display; pair with `GlSharing::Context`.
+    GlxDisplay(*mut libc::c_void),
+    EglDisplay(*mut libc::c_void),
+    WglHdc(*mut libc::c_void),
+    /// The `GLXContext`, `EGLContext` or `HGLRC` to share with.
+    Context(*mut libc::c_void),
+    CglSharegroup(*mut libc::c_void),
 }
 
-unsafe impl Sync for Device {}
-unsafe impl Send for Device {}
+/// Properties of a new context (`cl_context_properties`). The list passed
+/// to OpenCL is zero-terminated automatically.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct ContextProperties {
+    props: Vec<(cl_context_properties, cl_context_properties)>,
+}
 
-impl Device {
-    fn profile_info(&self, name: cl_device_info) -> String
+impl ContextProperties {
+    pub fn new() -> ContextProperties
     {
-        unsafe {
-            let mut size = 0 as libc::size_t;
-
-            let status = clGetDeviceInfo(
-                self.id,
-                name,
-                0,
-                ptr::null_mut(),
-                &mut size);
-            check(status, "Could not determine device info string length");
-
-            let mut buf : Vec<u8>
-                = repeat(0u8).take(size as usize).collect();
-
-            let status = clGetDeviceInfo(self.id,
-                                         name,
-                                         size,
-                                         buf.as_mut_ptr() as *mut libc::c_void,
-                                         ptr::null_mut());
-            check(status, "Could not get device info string");
-
-            String::from_utf8_unchecked(buf)
-        }
+        ContextProperties::default()
     }
 
-    pub fn name(&self) -> String
+    /// The platform to create the context on (`CL_CONTEXT_PLATFORM`).
+    pub fn platform(self, platform: &Platform) -> ContextProperties
     {
-        self.profile_info(CL_DEVICE_NAME)
+        self.property(CL_CONTEXT_PLATFORM, platform.get_id() as cl_context_properties)
     }
-    pub fn vendor(&self) -> String
+
+    /// Where kernels' `printf` output goes on ARM devices. Requires
+    /// `cl_arm_printf`.
+    pub fn arm_printf_callback(self, callback: ArmPrintfCallback) -> ContextProperties
     {
-        self.profile_info(CL_DEVICE_VENDOR)
+        self.property(cl_arm_printf::CL_PRINTF_CALLBACK_ARM as cl_context_properties,
+                      callback as cl_context_properties)
     }
-    pub fn profile(&self) -> String
+
+    /// Size in bytes of the buffer kernels' `printf` output is collected in
+    /// on ARM devices. Requires `cl_arm_printf`.
+    pub fn arm_printf_buffer_size(self, size: usize) -> ContextProperties
     {
-        self.profile_info(CL_DEVICE_PROFILE)
+        self.property(cl_arm_printf::CL_PRINTF_BUFFERSIZE_ARM as cl_context_properties,
+                      size as cl_context_properties)
     }
-    pub fn device_type(&self) -> String
+
+    /// Share objects with an OpenGL context. Requires `cl_khr_gl_sharing`;
+    /// usually set twice, with the display and the context.
+    pub fn gl_sharing(self, handle: GlSharing) -> ContextProperties
     {
-        self.profile_info(CL_DEVICE_TYPE)
+        use ext::cl_khr_gl_sharing::*;
+        let (key, value) = match handle {
+            GlSharing::GlxDisplay(h) => (CL_GLX_DISPLAY_KHR, h),
+            GlSharing::EglDisplay(h) => (CL_EGL_DISPLAY_KHR, h),
+            GlSharing::WglHdc(h) => (CL_WGL_HDC_KHR, h),
+            GlSharing::Context(h) => (CL_GL_CONTEXT_KHR, h),
+            GlSharing::CglSharegroup(h) => (CL_CGL_SHAREGROUP_KHR, h),
+        };
+        self.property(key, value as cl_context_properties)
     }
 
-    pub fn compute_units(&self) -> usize {
-		unsafe {
-			let mut ct: usize = 0;
-            let status = clGetDeviceInfo(
-                self.id,
-                CL_DEVICE_MAX_COMPUTE_UNITS,
-                8,
-                (&mut ct as *mut usize) as *mut libc::c_void,
-                ptr::null_mut());
-            check(status, "Could not get number of device compute units.");
-			return ct;
-		}
-	}
+    /// Sets a raw property, replacing any earlier value for `key`.
+    pub fn property(mut self, key: cl_context_properties, value: cl_context_properties)
+                    -> ContextProperties
+    {
+        self.props.retain(|&(k, _)| k != key);
+        self.props.push((key, value));
+        self
+    }
 
+    /// The value set for `key`, if any.
+    pub fn get(&self, key: cl_context_properties) -> Option<cl_context_properties>
+    {
+        self.props.iter().find(|&&(k, _)| k == key).map(|&(_, v)| v)
+    }
 
-    pub fn create_context(&self) -> Context
+    /// Reads back a zero-terminated list as returned by `clGetContextInfo`.
+    fn from_raw(raw: &[cl_context_properties]) -> ContextProperties
     {
-        unsafe
-        {
-            // TODO: Support for multiple devices
-            let mut errcode = 0;
+        let mut props = ContextProperties::new();
+        for pair in raw.chunks(2) {
+            if pair.len() < 2 || pair[0] == 0 {
+                break;
+            }
+            props = props.property(pair[0], pair[1]);
+        }
+        props
+    }
 
-            // TODO: Proper error messages
-            let ctx = clCreateContext(ptr::null(),
-                                      1,
-                                      &self.id,
-                                      mem::transmute(ptr::null::<fn()>()),
-                                      ptr::null_mut(),
-                                      (&mut errcode));
+    /// The zero-terminated list passed to `clCreateContext`.
+    fn to_raw(&self) -> Vec<cl_context_properties>
+    {
+        let mut raw = Vec::with_capacity(self.props.len() * 2 + 1);
+        for &(k, v) in self.props.iter() {
+            raw.push(k);
+            raw.push(v);
+        }
+        raw.push(0);
+        raw
+    }
+}
 
-            check(errcode, "Failed to create opencl context!");
+/// Floating-point capabilities of a device (`cl_device_fp_config`).
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct FpConfig {
+    pub denorm: bool,
+    pub inf_nan: bool,
+    pub round_to_nearest: bool,
+    pub round_to_zero: bool,
+    pub round_to_inf: bool,
+    pub fma: bool,
+    pub soft_float: bool,
+}
 
-            Context { ctx: ctx }
+impl FpConfig {
+    fn from_cl(bits: cl_device_fp_config) -> FpConfig {
+        FpConfig {
+            denorm: bits & CL_FP_DENORM != 0,
+            inf_nan: bits & CL_FP_INF_NAN != 0,
+            round_to_nearest: bits & CL_FP_ROUND_TO_NEAREST != 0,
+            round_to_zero: bits & CL_FP_ROUND_TO_ZERO != 0,
+            round_to_inf: bits & CL_FP_ROUND_TO_INF != 0,
+            fma: bits & CL_FP_FMA != 0,
+            soft_float: bits & CL_FP_SOFT_FLOAT != 0,
         }
     }
 }
 
-pub struct Context {
-    pub ctx: cl_context,
+/// Which kinds of kernels a device can run (`cl_device_exec_capabilities`).
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct ExecCapabilities {
+    pub kernel: bool,
+    pub native_kernel: bool,
 }
 
-unsafe impl Sync for Context {}
-unsafe impl Send for Context {}
-
-impl Context {
-    pub fn create_buffer<T>(&self, size: usize, flags: cl_mem_flags) -> CLBuffer<T>
-    {
-        unsafe {
-            let mut status = 0;
-            let buf = clCreateBuffer(self.ctx,
-                                     flags,
-                                     (size*mem::size_of::<T>()) as libc::size_t ,
-                                     ptr::null_mut(),
-                                     (&mut status));
-            check(status, "Could not allocate buffer");
-            CLBuffer {
-                cl_buffer: buf,
-                phantom: PhantomData,
-            }
+impl ExecCapabilities {
+    fn from_cl(bits: cl_device_exec_capabilities) -> ExecCapabilities {
+        ExecCapabilities {
+            kernel: bits & CL_EXEC_KERNEL != 0,
+            native_kernel: bits & CL_EXEC_NATIVE_KERNEL != 0,
         }
     }
+}
 
+/// Command-queue properties (`cl_command_queue_properties`).
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+pub struct QueueProperties {
+    /// Commands may run in any order not ruled out by their event lists.
+    pub out_of_order: bool,
+    /// Events record the timing information returned by `Event::*_time`.
+    pub profiling: bool,
+}
 
-    pub fn create_buffer_from<T, U, IN: Put<T, U>>(&self, create: IN, flags: cl_mem_flags) -> U
-    {
-        create.put(|p, len| {
-            let mut status = 0;
-            let buf = unsafe {
-                clCreateBuffer(self.ctx,
-                               flags | CL_MEM_COPY_HOST_PTR,
-                               len,
-                               mem::transmute(p),
-                               (&mut status))
-            };
-            check(status, "Could not allocate buffer");
-            buf
-        })
+impl QueueProperties {
+    /// An in-order queue without profiling.
+    pub fn new() -> QueueProperties {
+        QueueProperties::default()
     }
 
-    pub fn create_command_queue(&self, device: &Device) -> CommandQueue
-    {
-        unsafe
-        {
-            let mut errcode = 0;
+    fn from_cl(bits: cl_command_queue_properties) -> QueueProperties {
+        QueueProperties {
+            out_of_order: bits & CL_QUEUE_OUT_OF_ORDER_EXEC_MODE_ENABLE != 0,
+            profiling: bits & CL_QUEUE_PROFILING_ENABLE != 0,
+        }
+    }
 
-            let cqueue = clCreateCommandQueue(self.ctx,
-                                              device.id,
-                                              CL_QUEUE_PROFILING_ENABLE,
-                                              (&mut errcode));
+    fn to_cl(&self) -> cl_command_queue_properties {
+        let mut bits = 0;
+        if self.out_of_order {
+            bits |= CL_QUEUE_OUT_OF_ORDER_EXEC_MODE_ENABLE;
+        }
+        if self.profiling {
+            bits |= CL_QUEUE_PROFILING_ENABLE;
+        }
+        bits
+    }
+}
 
-            check(errcode, "Failed to create command queue!");
+/// Type of a device's global memory cache (`cl_device_mem_cache_type`).
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum MemCacheType {
+    None,
+    ReadOnly,
+    ReadWrite,
+}
 
-            CommandQueue {
-                cqueue: cqueue
-            }
+impl MemCacheType {
+    fn from_cl(t: cl_device_mem_cache_type) -> MemCacheType {
+        match t {
+            t if t == CL_READ_ONLY_CACHE => MemCacheType::ReadOnly,
+            t if t == CL_READ_WRITE_CACHE => MemCacheType::ReadWrite,
+            _ => MemCacheType::None
         }
     }
+}
 
-    pub fn create_program_from_source(&self, src: &str) -> Program
-    {
-        unsafe
-        {
-            let src = CString::new(src).unwrap();
+/// Type of a device's local memory (`cl_device_local_mem_type`).
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum LocalMemType {
+    None,
+    /// Dedicated local memory.
+    Local,
+    /// Local memory emulated in global memory.
+    Global,
+}
 
-            let mut status = CL_SUCCESS as cl_int;
-            let program = clCreateProgramWithSource(
-                self.ctx,
-                1,
-                &src.as_ptr(),
-                ptr::null(),
-                (&mut status));
-            check(status, "Could not create program");
+impl LocalMemType {
+    fn from_cl(t: cl_device_local_mem_type) -> LocalMemType {
+        match t {
+            t if t == CL_LOCAL => LocalMemType::Local,
+            t if t == CL_GLOBAL => LocalMemType::Global,
+            _ => LocalMemType::None
+        }
+    }
+}
 
-            Program { prg: program }
+/// A level of the memory hierarchy a device can be partitioned along
+/// (`cl_device_affinity_domain`).
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum AffinityDomain {
+    Numa,
+    L4Cache,
+    L3Cache,
+    L2Cache,
+    L1Cache,
+    /// The first of the levels above, from `Numa` down, that the device can
+    /// be split along.
+    NextPartitionable,
+}
+
+static AFFINITY_DOMAINS: [AffinityDomain; 6] = [
+    AffinityDomain::Numa, AffinityDomain::L4Cache, AffinityDomain::L3Cache,
+    AffinityDomain::L2Cache, AffinityDomain::L1Cache, AffinityDomain::NextPartitionable];
+
+impl AffinityDomain {
+    fn to_cl(self) -> cl_device_affinity_domain {
+        match self {
+            AffinityDomain::Numa => CL_DEVICE_AFFINITY_DOMAIN_NUMA,
+            AffinityDomain::L4Cache => CL_DEVICE_AFFINITY_DOMAIN_L4_CACHE,
+            AffinityDomain::L3Cache => CL_DEVICE_AFFINITY_DOMAIN_L3_CACHE,
+            AffinityDomain::L2Cache => CL_DEVICE_AFFINITY_DOMAIN_L2_CACHE,
+            AffinityDomain::L1Cache => CL_DEVICE_AFFINITY_DOMAIN_L1_CACHE,
+            AffinityDomain::NextPartitionable => CL_DEVICE_AFFINITY_DOMAIN_NEXT_PARTITIONABLE,
         }
     }
 
-    pub fn create_program_from_binary(&self, bin: &str, device: &Device) -> Program {
-        let src = CString::new(bin).unwrap();
-        let mut status = CL_SUCCESS as cl_int;
-        let len = bin.len() as libc::size_t;
-        let program = unsafe {
-            clCreateProgramWithBinary(
-                self.ctx,
-                1,
-                &device.id,
-                (&len),
-                (src.as_ptr() as *const *const i8) as *const *const libc::c_uchar,
-                ptr::null_mut(),
-                (&mut status))
-        };
-        check(status, "Could not create program");
+    fn to_ext(self) -> cl_device_partition_property_ext {
+        use ext::cl_ext_device_fission::*;
+        (match self {
+            AffinityDomain::Numa => CL_AFFINITY_DOMAIN_NUMA_EXT,
+            AffinityDomain::L4Cache => CL_AFFINITY_DOMAIN_L4_CACHE_EXT,
+            AffinityDomain::L3Cache => CL_AFFINITY_DOMAIN_L3_CACHE_EXT,
+            AffinityDomain::L2Cache => CL_AFFINITY_DOMAIN_L2_CACHE_EXT,
+            AffinityDomain::L1Cache => CL_AFFINITY_DOMAIN_L1_CACHE_EXT,
+            AffinityDomain::NextPartitionable => CL_AFFINITY_DOMAIN_NEXT_FISSIONABLE_EXT,
+        }) as cl_device_partition_property_ext
+    }
+
+    fn from_cl(bits: cl_device_affinity_domain) -> Option<AffinityDomain> {
+        AFFINITY_DOMAINS.iter().find(|d| d.to_cl() == bits).cloned()
+    }
 
-        Program {prg: program}
+    fn from_ext(value: cl_device_partition_property_ext) -> Option<AffinityDomain> {
+        AFFINITY_DOMAINS.iter().find(|d| d.to_ext() == value).cloned()
     }
 }
 
-impl Drop for Context
-{
-    fn drop(&mut self) {
-        unsafe {
-            clReleaseContext(self.ctx);
+/// A way of splitting a device into sub-devices.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum PartitionKind {
+    Equally,
+    ByCounts,
+    ByAffinityDomain,
+}
+
+impl PartitionKind {
+    /// The kind named by a `CL_DEVICE_PARTITION_PROPERTIES` entry.
+    pub fn from_cl(p: cl_device_partition_property) -> Option<PartitionKind> {
+        match p {
+            p if p == CL_DEVICE_PARTITION_EQUALLY => Some(PartitionKind::Equally),
+            p if p == CL_DEVICE_PARTITION_BY_COUNTS => Some(PartitionKind::ByCounts),
+            p if p == CL_DEVICE_PARTITION_BY_AFFINITY_DOMAIN =>
+                Some(PartitionKind::ByAffinityDomain),
+            _ => None
         }
     }
-}
 
-impl<'r, T> KernelArg for &'r (Buffer<T> + 'r) {
-    fn get_value(&self) -> (libc::size_t, *const libc::c_void)
-    {
-        unsafe {
-            (mem::size_of::<cl_mem>() as libc::size_t,
-             self.id_ptr() as *const libc::c_void)
+    /// The kind named by a `CL_DEVICE_PARTITION_TYPES_EXT` entry.
+    pub fn from_ext(p: cl_device_partition_property_ext) -> Option<PartitionKind> {
+        use ext::cl_ext_device_fission::*;
+        match p {
+            p if p == CL_DEVICE_PARTITION_EQUALLY_EXT => Some(PartitionKind::Equally),
+            p if p == CL_DEVICE_PARTITION_BY_COUNTS_EXT => Some(PartitionKind::ByCounts),
+            p if p == CL_DEVICE_PARTITION_BY_AFFINITY_DOMAIN_EXT =>
+                Some(PartitionKind::ByAffinityDomain),
+            _ => None
         }
     }
 }
 
-impl<'r, T> KernelArg for Box<Buffer<T> + 'r> {
-    fn get_value(&self) -> (libc::size_t, *const libc::c_void)
-    {
-        unsafe {
-            (mem::size_of::<cl_mem>() as libc::size_t,
-             self.id_ptr() as *const libc::c_void)
+/// How a sub-device was split off its parent.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum PartitionType {
+    /// Sub-devices of this many compute units each.
+    Equally(u32),
+    /// One sub-device per count, with that many compute units.
+    ByCounts(Vec<u32>),
+    /// One sub-device per instance of the given memory hierarchy level.
+    ByAffinityDomain(AffinityDomain),
+}
+
+impl PartitionType {
+    fn to_cl(&self) -> Vec<cl_device_partition_property> {
+        match *self {
+            PartitionType::Equally(n) =>
+                vec![CL_DEVICE_PARTITION_EQUALLY, n as cl_device_partition_property, 0],
+            PartitionType::ByCounts(ref counts) => {
+                let mut props = vec![CL_DEVICE_PARTITION_BY_COUNTS];
+                props.extend(counts.iter().map(|&n| n as cl_device_partition_property));
+                props.push(CL_DEVICE_PARTITION_BY_COUNTS_LIST_END);
+                props.push(0);
+                props
+            }
+            PartitionType::ByAffinityDomain(domain) =>
+                vec![CL_DEVICE_PARTITION_BY_AFFINITY_DOMAIN,
+                     domain.to_cl() as cl_device_partition_property, 0],
+        }
+    }
+
+    fn to_ext(&self) -> Vec<cl_device_partition_property_ext> {
+        use ext::cl_ext_device_fission::*;
+        match *self {
+            PartitionType::Equally(n) =>
+                vec![CL_DEVICE_PARTITION_EQUALLY_EXT, n as cl_device_partition_property_ext,
+                     CL_PROPERTIES_LIST_END_EXT],
+            PartitionType::ByCounts(ref counts) => {
+                let mut props = vec![CL_DEVICE_PARTITION_BY_COUNTS_EXT];
+                props.extend(counts.iter().map(|&n| n as cl_device_partition_property_ext));
+                props.push(CL_PARTITION_BY_COUNTS_LIST_END_EXT);
+                props.push(CL_PROPERTIES_LIST_END_EXT);
+                props
+            }
+            PartitionType::ByAffinityDomain(domain) =>
+                vec![CL_DEVICE_PARTITION_BY_AFFINITY_DOMAIN_EXT, domain.to_ext(),
+                     CL_PROPERTIES_LIST_END_EXT],
+        }
+    }
+
+    fn from_cl(props: &[cl_device_partition_property]) -> Option<PartitionType> {
+        match props.first() {
+            Some(&p) if p == CL_DEVICE_PARTITION_EQUALLY =>
+                props.get(1).map(|&n| PartitionType::Equally(n as u32)),
+            Some(&p) if p == CL_DEVICE_PARTITION_BY_COUNTS =>
+                Some(PartitionType::ByCounts(
+                    props[1..].iter()
+                        .take_while(|&&n| n != CL_DEVICE_PARTITION_BY_COUNTS_LIST_END)
+                        .map(|&n| n as u32)
+                        .collect())),
+            Some(&p) if p == CL_DEVICE_PARTITION_BY_AFFINITY_DOMAIN =>
+                props.get(1)
+                    .and_then(|&d| AffinityDomain::from_cl(d as cl_device_affinity_domain))
+                    .map(PartitionType::ByAffinityDomain),
+            _ => None
+        }
+    }
+
+    fn from_ext(props: &[cl_device_partition_property_ext]) -> Option<PartitionType> {
+        use ext::cl_ext_device_fission::*;
+        match props.first() {
+            Some(&p) if p == CL_DEVICE_PARTITION_EQUALLY_EXT =>
+                props.get(1).map(|&n| PartitionType::Equally(n as u32)),
+            Some(&p) if p == CL_DEVICE_PARTITION_BY_COUNTS_EXT =>
+                Some(PartitionType::ByCounts(
+                    props[1..].iter()
+                        .take_while(|&&n| n != CL_PARTITION_BY_COUNTS_LIST_END_EXT)
+                        .map(|&n| n as u32)
+                        .collect())),
+            Some(&p) if p == CL_DEVICE_PARTITION_BY_AFFINITY_DOMAIN_EXT =>
+                props.get(1)
+                    .and_then(|&d| AffinityDomain::from_ext(d))
+                    .map(PartitionType::ByAffinityDomain),
+            _ => None
         }
     }
 }
 
+/// The entry points used to partition a device: the core ones from OpenCL
+/// 1.2, or those of `cl_ext_device_fission` on older implementations.
+#[derive(Copy, Clone)]
+enum Fission {
+    Core(cl_1_2::DeviceFission),
+    Ext(cl_ext_device_fission::Functions),
+}
 
-pub struct CommandQueue {
-    pub cqueue: cl_command_queue
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub struct Device {
+    id: cl_device_id
 }
 
-unsafe impl Sync for CommandQueue {}
-unsafe impl Send for CommandQueue {}
+unsafe impl Sync for Device {}
+unsafe impl Send for Device {}
 
-impl CommandQueue
-{
-    //synchronous
-    pub fn enqueue_kernel<I: KernelIndex, E: EventList>(&self, k: &Kernel, global: I, local: Option<I>, wait_on: E)
-        -> Event
+impl Device {
+    fn info<T: Copy>(&self, name: cl_device_info) -> Result<T, Error>
     {
-        unsafe
-        {
-            wait_on.as_event_list(|event_list, event_list_length| {
-                let mut e: cl_event = ptr::null_mut();
-                let mut status = clEnqueueNDRangeKernel(
-                    self.cqueue,
-                    k.kernel,
-                    KernelIndex::num_dimensions(None::<I>),
-                    ptr::null(),
-                    global.get_ptr(),
-                    match local {
-                        Some(ref l) => l.get_ptr() as *const libc::size_t,
-                        None => ptr::null()
-                    },
-                    event_list_length,
-                    event_list,
-                    (&mut e));
-                check(status, "Error enqueuing kernel.");
-                status = clFinish(self.cqueue);
-                check(status, "Error finishing kernel.");
-                Event { event: e }
-            })
-        }
+        get_info("clGetDeviceInfo", name, |size, value, size_ret| unsafe {
+            clGetDeviceInfo(self.id, name, size, value, size_ret)
+        })
     }
 
-    //asynchronous
-    pub fn enqueue_async_kernel<I: KernelIndex, E: EventList>(&self, k: &Kernel, global: I, local: Option<I>, wait_on: E)
-        -> Event
+    fn info_vec<T: Copy>(&self, name: cl_device_info) -> Result<Vec<T>, Error>
     {
-        unsafe
-        {
-            wait_on.as_event_list(|event_list, event_list_length| {
-                let mut e: cl_event = ptr::null_mut();
-                let status = clEnqueueNDRangeKernel(
-                    self.cqueue,
-                    k.kernel,
-                    KernelIndex::num_dimensions(None::<I>),
-                    ptr::null(),
-                    global.get_ptr(),
-                    match local {
-                        Some(ref l) => l.get_ptr() as *const libc::size_t,
-                        None => ptr::null()
-                    },
-                    event_list_length,
-                    event_list,
-                    (&mut e));
-                check(status, "Error enqueuing kernel.");
-                Event { event: e }
-            })
-        }
+        get_info_vec("clGetDeviceInfo", name, |size, value, size_ret| unsafe {
+            clGetDeviceInfo(self.id, name, size, value, size_ret)
+        })
     }
 
-    pub fn get<T, U, B: Buffer<T>, G: Get<B, U>, E: EventList>(&self, buf: &B, event: E) -> G
+    fn info_bool(&self, name: cl_device_info) -> Result<bool, Error>
     {
-        event.as_event_list(|event_list, event_list_length| {
-            Get::get(buf, |offset, ptr, len| {
-                unsafe {
-                    let err = clEnqueueReadBuffer(self.cqueue,
-                                                  buf.id(),
-                                                  CL_TRUE,
-                                                  offset as libc::size_t,
-                                                  len,
-                                                  ptr,
-                                                  event_list_length,
-                                                  event_list,
-                                                  ptr::null_mut());
+        self.info::<cl_bool>(name).map(|b| b != CL_FALSE)
+    }
+
+    fn profile_info(&self, name: cl_device_info) -> Result<String, Error>
+    {
+        get_info_string("clGetDeviceInfo", name, |size, value, size_ret| unsafe {
+            clGetDeviceInfo(self.id, name, size, value, size_ret)
+        })
+    }
+
+    pub fn name(&self) -> Result<String, Error>
+    {
+        self.profile_info(CL_DEVICE_NAME)
+    }
+    pub fn vendor(&self) -> Result<String, Error>
+    {
+        self.profile_info(CL_DEVICE_VENDOR)
+    }
+    pub fn profile(&self) -> Result<String, Error>
+    {
+        self.profile_info(CL_DEVICE_PROFILE)
+    }
+    pub fn driver_version(&self) -> Result<String, Error>
+    {
+        self.profile_info(CL_DRIVER_VERSION)
+    }
+    pub fn version(&self) -> Result<String, Error>
+    {
+        self.profile_info(CL_DEVICE_VERSION)
+    }
+    pub fn opencl_c_version(&self) -> Result<String, Error>
+    {
+        self.profile_info(CL_DEVICE_OPENCL_C_VERSION)
+    }
+
+    /// The OpenCL version the device supports.
+    pub fn cl_version(&self) -> Result<ClVersion, Error>
+    {
+        let version = try!(self.version());
+        ClVersion::parse_info(&version, "clGetDeviceInfo")
+    }
+
+    /// The highest OpenCL C version the device's compiler supports.
+    pub fn cl_c_version(&self) -> Result<ClVersion, Error>
+    {
+        let version = try!(self.opencl_c_version());
+        ClVersion::parse_info(&version, "clGetDeviceInfo")
+    }
+
+    /// Fails with a descriptive error unless the device supports at least
+    /// `required`, the version needed by the entry point `api`.
+    pub fn require_version(&self, required: ClVersion, api: &'static str) -> Result<(), Error>
+    {
+        let version = try!(self.cl_version());
+        version.require(required, api, "device")
+    }
+    pub fn extensions(&self) -> Result<String, Error>
+    {
+        self.profile_info(CL_DEVICE_EXTENSIONS)
+    }
+
+    /// The device's extensions, one name per entry.
+    pub fn extensions_set(&self) -> Result<HashSet<String>, Error>
+    {
+        self.extensions().map(|e| parse_extensions(&e))
+    }
+
+    /// Whether the device lists exactly the extension `name`.
+    pub fn supports(&self, name: &str) -> Result<bool, Error>
+    {
+        self.extensions_set().map(|set| set.contains(name))
+    }
+
+    /// The types this device reports, e.g. `[GPU]` or `[Default, GPU]`.
+    pub fn device_type(&self) -> Result<Vec<DeviceType>, Error>
+    {
+        self.info::<cl_device_type>(CL_DEVICE_TYPE).map(decode_device_type)
+    }
+
+    pub fn platform(&self) -> Result<Platform, Error>
+    {
+        self.info::<cl_platform_id>(CL_DEVICE_PLATFORM).map(|id| Platform { id: id })
+    }
+
+    pub fn compute_units(&self) -> Result<usize, Error> {
+        self.max_compute_units().map(|n| n as usize)
+    }
+
+    pub fn vendor_id(&self) -> Result<u32, Error>
+    {
+        self.info::<cl_uint>(CL_DEVICE_VENDOR_ID)
+    }
+
+    pub fn max_compute_units(&self) -> Result<u32, Error>
+    {
+        self.info::<cl_uint>(CL_DEVICE_MAX_COMPUTE_UNITS)
+    }
+
+    pub fn max_work_item_dimensions(&self) -> Result<u32, Error>
+    {
+        self.info::<cl_uint>(CL_DEVICE_MAX_WORK_ITEM_DIMENSIONS)
+    }
+
+    pub fn max_work_group_size(&self) -> Result<usize, Error>
+    {
+        self.info::<usize>(CL_DEVICE_MAX_WORK_GROUP_SIZE)
+    }
+
+    /// The maximum work-item count in each dimension of a work-group.
+    pub fn max_work_item_sizes(&self) -> Result<Vec<usize>, Error>
+    {
+        self.info_vec::<usize>(CL_DEVICE_MAX_WORK_ITEM_SIZES)
+    }
+
+    pub fn preferred_vector_width_char(&self) -> Result<u32, Error>
+    {
+        self.info::<cl_uint>(CL_DEVICE_PREFERRED_VECTOR_WIDTH_CHAR)
+    }
+
+    pub fn preferred_vector_width_short(&self) -> Result<u32, Error>
+    {
+        self.info::<cl_uint>(CL_DEVICE_PREFERRED_VECTOR_WIDTH_SHORT)
+    }
+
+    pub fn preferred_vector_width_int(&self) -> Result<u32, Error>
+    {
+        self.info::<cl_uint>(CL_DEVICE_PREFERRED_VECTOR_WIDTH_INT)
+    }
+
+    pub fn preferred_vector_width_long(&self) -> Result<u32, Error>
+    {
+        self.info::<cl_uint>(CL_DEVICE_PREFERRED_VECTOR_WIDTH_LONG)
+    }
+
+    pub fn preferred_vector_width_float(&self) -> Result<u32, Error>
+    {
+        self.info::<cl_uint>(CL_DEVICE_PREFERRED_VECTOR_WIDTH_FLOAT)
+    }
+
+    pub fn preferred_vector_width_double(&self) -> Result<u32, Error>
+    {
+        self.info::<cl_uint>(CL_DEVICE_PREFERRED_VECTOR_WIDTH_DOUBLE)
+    }
+
+    pub fn preferred_vector_width_half(&self) -> Result<u32, Error>
+    {
+        self.info::<cl_uint>(CL_DEVICE_PREFERRED_VECTOR_WIDTH_HALF)
+    }
+
+    pub fn native_vector_width_char(&self) -> Result<u32, Error>
+    {
+        self.info::<cl_uint>(CL_DEVICE_NATIVE_VECTOR_WIDTH_CHAR)
+    }
+
+    pub fn native_vector_width_short(&self) -> Result<u32, Error>
+    {
+        self.info::<cl_uint>(CL_DEVICE_NATIVE_VECTOR_WIDTH_SHORT)
+    }
+
+    pub fn native_vector_width_int(&self) -> Result<u32, Error>
+    {
+        self.info::<cl_uint>(CL_DEVICE_NATIVE_VECTOR_WIDTH_INT)
+    }
+
+    pub fn native_vector_width_long(&self) -> Result<u32, Error>
+    {
+        self.info::<cl_uint>(CL_DEVICE_NATIVE_VECTOR_WIDTH_LONG)
+    }
+
+    pub fn native_vector_width_float(&self) -> Result<u32, Error>
+    {
+        self.info::<cl_uint>(CL_DEVICE_NATIVE_VECTOR_WIDTH_FLOAT)
+    }
+
+    pub fn native_vector_width_double(&self) -> Result<u32, Error>
+    {
+        self.info::<cl_uint>(CL_DEVICE_NATIVE_VECTOR_WIDTH_DOUBLE)
+    }
+
+    pub fn native_vector_width_half(&self) -> Result<u32, Error>
+    {
+        self.info::<cl_uint>(CL_DEVICE_NATIVE_VECTOR_WIDTH_HALF)
+    }
+
+    /// In MHz.
+    pub fn max_clock_frequency(&self) -> Result<u32, Error>
+    {
+        self.info::<cl_uint>(CL_DEVICE_MAX_CLOCK_FREQUENCY)
+    }
+
+    pub fn address_bits(&self) -> Result<u32, Error>
+    {
+        self.info::<cl_uint>(CL_DEVICE_ADDRESS_BITS)
+    }
+
+    /// In bytes.
+    pub fn max_mem_alloc_size(&self) -> Result<u64, Error>
+    {
+        self.info::<cl_ulong>(CL_DEVICE_MAX_MEM_ALLOC_SIZE)
+    }
+
+    pub fn image_support(&self) -> Result<bool, Error>
+    {
+        self.info_bool(CL_DEVICE_IMAGE_SUPPORT)
+    }
+
+    pub fn max_read_image_args(&self) -> Result<u32, Error>
+    {
+        self.info::<cl_uint>(CL_DEVICE_MAX_READ_IMAGE_ARGS)
+    }
+
+    pub fn max_write_image_args(&self) -> Result<u32, Error>
+    {
+        self.info::<cl_uint>(CL_DEVICE_MAX_WRITE_IMAGE_ARGS)
+    }
+
+    pub fn image2d_max_width(&self) -> Result<usize, Error>
+    {
+        self.info::<usize>(CL_DEVICE_IMAGE2D_MAX_WIDTH)
+    }
+
+    pub fn image2d_max_height(&self) -> Result<usize, Error>
+    {
+        self.info::<usize>(CL_DEVICE_IMAGE2D_MAX_HEIGHT)
+    }
+
+    pub fn image3d_max_width(&self) -> Result<usize, Error>
+    {
+        self.info::<usize>(CL_DEVICE_IMAGE3D_MAX_WIDTH)
+    }
+
+    pub fn image3d_max_height(&self) -> Result<usize, Error>
+    {
+        self.info::<usize>(CL_DEVICE_IMAGE3D_MAX_HEIGHT)
+    }
+
+    pub fn image3d_max_depth(&self) -> Result<usize, Error>
+    {
+        self.info::<usize>(CL_DEVICE_IMAGE3D_MAX_DEPTH)
+    }
+
+    pub fn max_samplers(&self) -> Result<u32, Error>
+    {
+        self.info::<cl_uint>(CL_DEVICE_MAX_SAMPLERS)
+    }
+
+    /// In bytes.
+    pub fn max_parameter_size(&self) -> Result<usize, Error>
+    {
+        self.info::<usize>(CL_DEVICE_MAX_PARAMETER_SIZE)
+    }
+
+    /// In bits.
+    pub fn mem_base_addr_align(&self) -> Result<u32, Error>
+    {
+        self.info::<cl_uint>(CL_DEVICE_MEM_BASE_ADDR_ALIGN)
+    }
+
+    /// In bytes.
+    pub fn min_data_type_align_size(&self) -> Result<u32, Error>
+    {
+        self.info::<cl_uint>(CL_DEVICE_MIN_DATA_TYPE_ALIGN_SIZE)
+    }
+
+    pub fn single_fp_config(&self) -> Result<FpConfig, Error>
+    {
+        self.info::<cl_device_fp_config>(CL_DEVICE_SINGLE_FP_CONFIG).map(FpConfig::from_cl)
+    }
+
+    /// Fails unless the device supports `cl_khr_fp64`.
+    pub fn double_fp_config(&self) -> Result<FpConfig, Error>
+    {
+        self.info::<cl_device_fp_config>(cl_khr_fp64::CL_DEVICE_DOUBLE_FP_CONFIG)
+            .map(FpConfig::from_cl)
+    }
+
+    /// Fails unless the device supports `cl_khr_fp16`.
+    pub fn half_fp_config(&self) -> Result<FpConfig, Error>
+    {
+        self.info::<cl_device_fp_config>(cl_khr_fp16::CL_DEVICE_HALF_FP_CONFIG)
+            .map(FpConfig::from_cl)
+    }
+
+    pub fn global_mem_cache_type(&self) -> Result<MemCacheType, Error>
+    {
+        self.info::<cl_device_mem_cache_type>(CL_DEVICE_GLOBAL_MEM_CACHE_TYPE)
+            .map(MemCacheType::from_cl)
+    }
+
+    /// In bytes.
+    pub fn global_mem_cacheline_size(&self) -> Result<u32, Error>
+    {
+        self.info::<cl_uint>(CL_DEVICE_GLOBAL_MEM_CACHELINE_SIZE)
+    }
+
+    /// In bytes.
+    pub fn global_mem_cache_size(&self) -> Result<u64, Error>
+    {
+        self.info::<cl_ulong>(CL_DEVICE_GLOBAL_MEM_CACHE_SIZE)
+    }
+
+    /// In bytes.
+    pub fn global_mem_size(&self) -> Result<u64, Error>
+    {
+        self.info::<cl_ulong>(CL_DEVICE_GLOBAL_MEM_SIZE)
+    }
+
+    /// In bytes.
+    pub fn max_constant_buffer_size(&self) -> Result<u64, Error>
+    {
+        self.info::<cl_ulong>(CL_DEVICE_MAX_CONSTANT_BUFFER_SIZE)
+    }
+
+    pub fn max_constant_args(&self) -> Result<u32, Error>
+    {
+        self.info::<cl_uint>(CL_DEVICE_MAX_CONSTANT_ARGS)
+    }
+
+    pub fn local_mem_type(&self) -> Result<LocalMemType, Error>
+    {
+        self.info::<cl_device_local_mem_type>(CL_DEVICE_LOCAL_MEM_TYPE)
+            .map(LocalMemType::from_cl)
+    }
+
+    /// In bytes.
+    pub fn local_mem_size(&self) -> Result<u64, Error>
+    {
+        self.info::<cl_ulong>(CL_DEVICE_LOCAL_MEM_SIZE)
+    }
+
+    pub fn error_correction_support(&self) -> Result<bool, Error>
+    {
+        self.info_bool(CL_DEVICE_ERROR_CORRECTION_SUPPORT)
+    }
+
+    /// In nanoseconds.
+    pub fn profiling_timer_resolution(&self) -> Result<usize, Error>
+    {
+        self.info::<usize>(CL_DEVICE_PROFILING_TIMER_RESOLUTION)
+    }
+
+    pub fn endian_little(&self) -> Result<bool, Error>
+    {
+        self.info_bool(CL_DEVICE_ENDIAN_LITTLE)
+    }
+
+    pub fn available(&self) -> Result<bool, Error>
+    {
+        self.info_bool(CL_DEVICE_AVAILABLE)
+    }
+
+    pub fn compiler_available(&self) -> Result<bool, Error>
+    {
+        self.info_bool(CL_DEVICE_COMPILER_AVAILABLE)
+    }
+
+    pub fn host_unified_memory(&self) -> Result<bool, Error>
+    {
+        self.info_bool(CL_DEVICE_HOST_UNIFIED_MEMORY)
+    }
+
+    pub fn execution_capabilities(&self) -> Result<ExecCapabilities, Error>
+    {
+        self.info::<cl_device_exec_capabilities>(CL_DEVICE_EXECUTION_CAPABILITIES)
+            .map(ExecCapabilities::from_cl)
+    }
+
+    /// The command-queue properties the device supports.
+    pub fn queue_properties(&self) -> Result<QueueProperties, Error>
+    {
+        self.info::<cl_command_queue_properties>(CL_DEVICE_QUEUE_PROPERTIES)
+            .map(QueueProperties::from_cl)
+    }
+
+    /// Fails with a descriptive error unless the device supports the
+    /// extension `name`.
+    fn require_extension(&self, name: &str) -> Result<(), Error>
+    {
+        if try!(self.supports(name)) {
+            Ok(())
+        } else {
+            Err(Error::new(CL_INVALID_OPERATION as cl_int, "clGetDeviceInfo",
+                           &format!("The device does not support {}", name)))
+        }
+    }
+
+    fn nv_info<T: Copy>(&self, name: cl_device_info) -> Result<T, Error>
+    {
+        try!(self.require_extension("cl_nv_device_attribute_query"));
+        self.info::<T>(name)
+    }
+
+    /// The CUDA compute capability as `(major, minor)`. Requires
+    /// `cl_nv_device_attribute_query`, as do the other `nv_*` queries.
+    pub fn nv_compute_capability(&self) -> Result<(u32, u32), Error>
+    {
+        use ext::cl_nv_device_attribute_query::*;
+        let major = try!(self.nv_info::<cl_uint>(CL_DEVICE_COMPUTE_CAPABILITY_MAJOR_NV));
+        let minor = try!(self.nv_info::<cl_uint>(CL_DEVICE_COMPUTE_CAPABILITY_MINOR_NV));
+        Ok((major, minor))
+    }
+
+    /// In work-items.
+    pub fn nv_warp_size(&self) -> Result<u32, Error>
+    {
+        self.nv_info::<cl_uint>(cl_nv_device_attribute_query::CL_DEVICE_WARP_SIZE_NV)
+    }
+
+    /// 32-bit registers available to a work-group.
+    pub fn nv_registers_per_block(&self) -> Result<u32, Error>
+    {
+        self.nv_info::<cl_uint>(cl_nv_device_attribute_query::CL_DEVICE_REGISTERS_PER_BLOCK_NV)
+    }
+
+    /// Whether the device can transfer data while running kernels.
+    pub fn nv_gpu_overlap(&self) -> Result<bool, Error>
+    {
+        self.nv_info::<cl_bool>(cl_nv_device_attribute_query::CL_DEVICE_GPU_OVERLAP_NV)
+            .map(|b| b != CL_FALSE)
+    }
+
+    /// Whether long-running kernels are killed, e.g. because the device
+    /// drives a display.
+    pub fn nv_kernel_exec_timeout(&self) -> Result<bool, Error>
+    {
+        self.nv_info::<cl_bool>(cl_nv_device_attribute_query::CL_DEVICE_KERNEL_EXEC_TIMEOUT_NV)
+            .map(|b| b != CL_FALSE)
+    }
+
+    /// Whether the device shares memory with the host.
+    pub fn nv_integrated_memory(&self) -> Result<bool, Error>
+    {
+        self.nv_info::<cl_bool>(cl_nv_device_attribute_query::CL_DEVICE_INTEGRATED_MEMORY_NV)
+            .map(|b| b != CL_FALSE)
+    }
+
+    /// Offset in nanoseconds between the device's profiling timer and the
+    /// host clock. Requires `cl_amd_device_attribute_query`.
+    pub fn amd_profiling_timer_offset(&self) -> Result<u64, Error>
+    {
+        try!(self.require_extension("cl_amd_device_attribute_query"));
+        self.info::<cl_ulong>(cl_amd_device_attribute_query::CL_DEVICE_PROFILING_TIMER_OFFSET_AMD)
+    }
+
+    /// How this device can be partitioned, if at all.
+    fn fission(&self) -> Result<Option<Fission>, Error>
+    {
+        let platform = try!(self.platform());
+        let core = ClVersion::new(1, 2);
+        if try!(self.cl_version()) >= core && try!(platform.cl_version()) >= core {
+            return cl_1_2::device_fission()
+                .map(|functions| Some(Fission::Core(functions)))
+                .map_err(|e| Error::new(CL_INVALID_OPERATION as cl_int, "dlsym", &e));
+        }
+
+        if !try!(self.supports("cl_ext_device_fission")) {
+            return Ok(None);
+        }
+        match cl_ext_device_fission::load(&platform) {
+            Ok(functions) => Ok(Some(Fission::Ext(functions))),
+            Err(e) => Err(Error::new(CL_INVALID_OPERATION as cl_int,
+                                     "clGetExtensionFunctionAddress", &e))
+        }
+    }
+
+    fn create_sub_devices(&self, partition: PartitionType) -> Result<Vec<SubDevice>, Error>
+    {
+        let fission = match try!(self.fission()) {
+            Some(fission) => fission,
+            None => return Err(Error::new(CL_INVALID_OPERATION as cl_int, "clCreateSubDevices",
+                                          "Partitioning a device requires OpenCL 1.2 or \
+                                           cl_ext_device_fission"))
+        };
+
+        let core = partition.to_cl();
+        let ext = partition.to_ext();
+        let (api, create) = match fission {
+            Fission::Core(functions) => ("clCreateSubDevices", Box::new(
+                move |num, out, num_ret| {
+                    (functions.clCreateSubDevices)(self.id, core.as_ptr(), num, out, num_ret)
+                }) as Box<Fn(cl_uint, *mut cl_device_id, *mut cl_uint) -> cl_int>),
+            Fission::Ext(functions) => ("clCreateSubDevicesEXT", Box::new(
+                move |num, out, num_ret| {
+                    (functions.clCreateSubDevicesEXT)(self.id, ext.as_ptr(), num, out, num_ret)
+                }) as Box<Fn(cl_uint, *mut cl_device_id, *mut cl_uint) -> cl_int>),
+        };
+
+        let message = format!("Could not partition device: {:?}", partition);
+        let mut count = 0;
+        try!(check_status(create(0, ptr::null_mut(), &mut count), api, &message));
+        let mut ids = vec![ptr::null_mut(); count as usize];
+        try!(check_status(create(count, ids.as_mut_ptr(), ptr::null_mut()), api, &message));
+
+        Ok(ids.into_iter()
+              .map(|id| SubDevice { device: Device { id: id }, fission: fission })
+              .collect())
+    }
+
+    /// Splits the device into as many sub-devices of `compute_units` compute
+    /// units each as fit.
+    pub fn partition_equally(&self, compute_units: u32) -> Result<Vec<SubDevice>, Error>
+    {
+        self.create_sub_devices(PartitionType::Equally(compute_units))
+    }
+
+    /// Splits the device into one sub-device per entry of `counts`, each with
+    /// that many compute units.
+    pub fn partition_by_counts(&self, counts: &[u32]) -> Result<Vec<SubDevice>, Error>
+    {
+        self.create_sub_devices(PartitionType::ByCounts(counts.to_vec()))
+    }
+
+    /// Splits the device into sub-devices sharing the given level of the
+    /// memory hierarchy.
+    pub fn partition_by_affinity(&self, domain: AffinityDomain) -> Result<Vec<SubDevice>, Error>
+    {
+        self.create_sub_devices(PartitionType::ByAffinityDomain(domain))
+    }
+
+    /// The device this one was partitioned from, or `None` for a root device.
+    pub fn parent_device(&self) -> Result<Option<Device>, Error>
+    {
+        let name = match try!(self.fission()) {
+            Some(Fission::Core(_)) => CL_DEVICE_PARENT_DEVICE,
+            Some(Fission::Ext(_)) => cl_ext_device_fission::CL_DEVICE_PARENT_DEVICE_EXT,
+            None => return Ok(None)
+        };
+        let id = try!(self.info::<cl_device_id>(name));
+        Ok(if id.is_null() { None } else { Some(Device { id: id }) })
+    }
+
+    /// The ways this device can be partitioned. Empty if it cannot be.
+    pub fn partition_kinds(&self) -> Result<Vec<PartitionKind>, Error>
+    {
+        let kinds: Vec<PartitionKind> = match try!(self.fission()) {
+            Some(Fission::Core(_)) => {
+                let props = try!(self.info_vec::<cl_device_partition_property>(
+                    CL_DEVICE_PARTITION_PROPERTIES));
+                props.into_iter().filter_map(PartitionKind::from_cl).collect()
+            }
+            Some(Fission::Ext(_)) => {
+                let props = try!(self.info_vec::<cl_device_partition_property_ext>(
+                    cl_ext_device_fission::CL_DEVICE_PARTITION_TYPES_EXT));
+                props.into_iter().filter_map(PartitionKind::from_ext).collect()
+            }
+            None => Vec::new()
+        };
+        Ok(kinds)
+    }
+
+    /// The levels of the memory hierarchy `partition_by_affinity` accepts.
+    pub fn partition_affinity_domains(&self) -> Result<Vec<AffinityDomain>, Error>
+    {
+        match try!(self.fission()) {
+            Some(Fission::Core(_)) => {
+                let bits = try!(self.info::<cl_device_affinity_domain>(
+                    CL_DEVICE_PARTITION_AFFINITY_DOMAIN));
+                Ok(AFFINITY_DOMAINS.iter().filter(|d| bits & d.to_cl() != 0).cloned().collect())
+            }
+            Some(Fission::Ext(_)) => {
+                let domains = try!(self.info_vec::<cl_device_partition_property_ext>(
+                    cl_ext_device_fission::CL_DEVICE_AFFINITY_DOMAINS_EXT));
+                Ok(domains.into_iter().filter_map(AffinityDomain::from_ext).collect())
+            }
+            None => Ok(Vec::new())
+        }
+    }
+
+    /// The most sub-devices a single partitioning may create. Requires
+    /// OpenCL 1.2.
+    pub fn partition_max_sub_devices(&self) -> Result<u32, Error>
+    {
+        try!(self.require_version(ClVersion::new(1, 2), "clGetDeviceInfo"));
+        self.info::<cl_uint>(CL_DEVICE_PARTITION_MAX_SUB_DEVICES)
+    }
+
+    /// How this device was split off its parent, or `None` for a root device.
+    pub fn partition_type(&self) -> Result<Option<PartitionType>, Error>
+    {
+        match try!(self.fission()) {
+            Some(Fission::Core(_)) => {
+                let props = try!(self.info_vec::<cl_device_partition_property>(
+                    CL_DEVICE_PARTITION_TYPE));
+                Ok(PartitionType::from_cl(&props[..]))
+            }
+            Some(Fission::Ext(_)) => {
+                let props = try!(self.info_vec::<cl_device_partition_property_ext>(
+                    cl_ext_device_fission::CL_DEVICE_PARTITION_STYLE_EXT));
+                Ok(PartitionType::from_ext(&props[..]))
+            }
+            None => Ok(None)
+        }
+    }
+
+    pub fn create_context(&self) -> Result<Context, Error>
+    {
+        Context::new(&[*self], ContextProperties::new())
+    }
+}
+
+/// A device created by partitioning another one. Dereferences to the
+/// `Device` and releases it when dropped.
+pub struct SubDevice {
+    device: Device,
+    fission: Fission,
+}
+
+impl SubDevice {
+    pub fn device(&self) -> Device
+    {
+        self.device
+    }
+}
+
+impl Deref for SubDevice {
+    type Target = Device;
+
+    fn deref(&self) -> &Device
+    {
+        &self.device
+    }
+}
+
+impl Clone for SubDevice {
+    fn clone(&self) -> SubDevice
+    {
+        match self.fission {
+            Fission::Core(functions) => (functions.clRetainDevice)(self.device.id),
+            Fission::Ext(functions) => (functions.clRetainDeviceEXT)(self.device.id)
+        };
+        SubDevice { device: self.device, fission: self.fission }
+    }
+}
+
+impl fmt::Debug for SubDevice {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "SubDevice({:?})", self.device)
+    }
+}
+
+impl Drop for SubDevice {
+    fn drop(&mut self) {
+        match self.fission {
+            Fission::Core(functions) => (functions.clReleaseDevice)(self.device.id),
+            Fission::Ext(functions) => (functions.clReleaseDeviceEXT)(self.device.id)
+        };
+    }
+}
+
+pub struct Context {
+    pub ctx: cl_context,
+    state: Arc<ContextState>,
+}
+
+/// What the Rust side keeps for a context. It is shared by every `Context`
+/// and `CommandQueue` handle to the context, including those obtained from
+/// queues and events, and dropped with the last of them. Buffers, programs
+/// and kernels do not keep it alive, so a notification closure must not be
+/// relied on once every context and queue handle is gone.
+struct ContextState {
+    /// Passed to `context_notify` as `user_data`.
+    notify: Option<Box<ContextNotify>>,
+    /// The kernels `CommandQueue::fill` uses without `clEnqueueFillBuffer`,
+    /// one per device. The lock is held while their arguments are set and
+    /// enqueued.
+    fill_kernels: Mutex<HashMap<Device, Kernel>>,
+}
+
+/// The state of every context that has a live handle, by context.
+type ContextRegistry = Mutex<HashMap<cl_context, Weak<ContextState>>>;
+
+fn context_registry() -> MutexGuard<'static, HashMap<cl_context, Weak<ContextState>>>
+{
+    static INIT: Once = Once::new();
+    static mut REGISTRY: *const ContextRegistry = 0 as *const ContextRegistry;
+    unsafe {
+        INIT.call_once(|| {
+            REGISTRY = Box::into_raw(Box::new(Mutex::new(HashMap::new())));
+        });
+        // The map stays consistent even if a holder panicked.
+        (*REGISTRY).lock().unwrap_or_else(|e| e.into_inner())
+    }
+}
+
+impl ContextState {
+    fn new(notify: Option<Box<ContextNotify>>) -> Arc<ContextState>
+    {
+        Arc::new(ContextState { notify: notify, fill_kernels: Mutex::new(HashMap::new()) })
+    }
+
+    /// Makes `state` the one `of(ctx)` returns while it is alive.
+    fn register(ctx: cl_context, state: &Arc<ContextState>)
+    {
+        let mut registry = context_registry();
+        registry.retain(|_, state| state.upgrade().is_some());
+        registry.insert(ctx, Arc::downgrade(state));
+    }
+
+    /// The state shared by the live handles to `ctx`, or a new one if there
+    /// are none.
+    fn of(ctx: cl_context) -> Arc<ContextState>
+    {
+        let mut registry = context_registry();
+        if let Some(state) = registry.get(&ctx).and_then(|state| state.upgrade()) {
+            return state;
+        }
+        let state = ContextState::new(None);
+        registry.insert(ctx, Arc::downgrade(&state));
+        state
+    }
+}
+
+unsafe impl Sync for Context {}
+unsafe impl Send for Context {}
+
+impl Context {
+    /// Creates a context spanning `devices`, which must all belong to the
+    /// same platform. `CL_CONTEXT_PLATFORM` defaults to that platform. Errors
+    /// reported by the implementation are logged.
+    pub fn new(devices: &[Device], properties: ContextProperties) -> Result<Context, Error>
+    {
+        Context::create(devices, properties, None)
+    }
+
+    /// Like `new`, but errors reported by the implementation are passed to
+    /// `notify` instead of the log.
+    pub fn with_notify<F>(devices: &[Device], properties: ContextProperties, notify: F)
+                          -> Result<Context, Error>
+        where F: Fn(&str, &[u8]) + Send + Sync + 'static
+    {
+        Context::create(devices, properties, Some(Box::new(notify)))
+    }
+
+    fn create(devices: &[Device], properties: ContextProperties,
+              notify: Option<Box<ContextNotify>>) -> Result<Context, Error>
+    {
+        if devices.is_empty() {
+            return Err(Error::new(CL_INVALID_VALUE as cl_int, "clCreateContext",
+                                  "A context needs at least one device"));
+        }
+
+        let platform = try!(devices[0].platform());
+        for device in devices[1..].iter() {
+            if try!(device.platform()).get_id() != platform.get_id() {
+                return Err(Error::new(CL_INVALID_DEVICE as cl_int, "clCreateContext",
+                                      "All devices of a context must belong to one platform"));
+            }
+        }
+
+        let properties = match properties.get(CL_CONTEXT_PLATFORM) {
+            Some(_) => properties,
+            None => properties.property(CL_CONTEXT_PLATFORM,
+                                        platform.get_id() as cl_context_properties)
+        };
+        create_context_notify(devices, &properties.to_raw()[..], notify)
+    }
+
+    /// Creates a context with every device of the given type. Unless
+    /// `properties` names a platform, the implementation picks one. Errors
+    /// reported by the implementation are logged.
+    pub fn from_type(device_type: DeviceType, properties: ContextProperties)
+                     -> Result<Context, Error>
+    {
+        let props = properties.to_raw();
+        let mut errcode = 0;
+        let ctx = unsafe {
+            clCreateContextFromType(props.as_ptr(),
+                                    convert_device_type(device_type),
+                                    context_notify,
+                                    ptr::null_mut(),
+                                    &mut errcode)
+        };
+        try!(check_status(errcode, "clCreateContextFromType",
+                          &format!("Failed to create opencl context for {:?} devices",
+                                   device_type)));
+        let state = ContextState::new(None);
+        ContextState::register(ctx, &state);
+        Ok(Context { ctx: ctx, state: state })
+    }
+
+    /// Wraps a context handle obtained from another object, taking a new
+    /// reference to it. The handle shares the state of the context's other
+    /// handles.
+    unsafe fn retain(ctx: cl_context) -> Context
+    {
+        clRetainContext(ctx);
+        Context { ctx: ctx, state: ContextState::of(ctx) }
+    }
+
+    fn info_vec<T: Copy>(&self, name: cl_context_info) -> Result<Vec<T>, Error>
+    {
+        get_info_vec("clGetContextInfo", name, |size, value, size_ret| unsafe {
+            clGetContextInfo(self.ctx, name, size, value, size_ret)
+        })
+    }
+
+    pub fn reference_count(&self) -> Result<u32, Error>
+    {
+        get_info("clGetContextInfo", CL_CONTEXT_REFERENCE_COUNT, |size, value, size_ret| unsafe {
+            clGetContextInfo(self.ctx, CL_CONTEXT_REFERENCE_COUNT, size, value, size_ret)
+        })
+    }
+
+    /// The devices this context was created for.
+    pub fn devices(&self) -> Result<Vec<Device>, Error>
+    {
+        let ids = try!(self.info_vec::<cl_device_id>(CL_CONTEXT_DEVICES));
+        Ok(ids.into_iter().map(|id| Device { id: id }).collect())
+    }
+
+    /// The properties the context was created with.
+    pub fn properties(&self) -> Result<ContextProperties, Error>
+    {
+        let raw = try!(self.info_vec::<cl_context_properties>(CL_CONTEXT_PROPERTIES));
+        Ok(ContextProperties::from_raw(&raw[..]))
+    }
+
+    pub fn create_buffer<T>(&self, size: usize, flags: cl_mem_flags) -> Result<CLBuffer<T>, Error>
+    {
+        unsafe {
+            let mut status = 0;
+            let buf = clCreateBuffer(self.ctx,
+                                     flags,
+                                     (size*mem::size_of::<T>()) as libc::size_t ,
+                                     ptr::null_mut(),
+                                     (&mut status));
+            try!(check_status(status, "clCreateBuffer", "Could not allocate buffer"));
+            Ok(CLBuffer {
+                cl_buffer: buf,
+                phantom: PhantomData,
+            })
+        }
+    }
+
+
+    pub fn create_buffer_from<T, U, IN: Put<T, U>>(&self, create: IN, flags: cl_mem_flags) -> Result<U, Error>
+    {
+        let mut status = 0;
+        let buf = create.put(|p, len| {
+            unsafe {
+                clCreateBuffer(self.ctx,
+                               flags | CL_MEM_COPY_HOST_PTR,
+                               len,
+                               mem::transmute(p),
+                               (&mut status))
+            }
+        });
+        if let Err(e) = check_status(status, "clCreateBuffer", "Could not allocate buffer") {
+            // The wrapper holds a null handle that must not be released.
+            mem::forget(buf);
+            return Err(e);
+        }
+        Ok(buf)
+    }
+
+    /// Creates an in-order queue with profiling enabled.
+    pub fn create_command_queue(&self, device: &Device) -> Result<CommandQueue, Error>
+    {
+        self.create_command_queue_with_properties(device, QueueProperties {
+            out_of_order: false,
+            profiling: true,
+        })
+    }
+
+    /// Fails with `CL_INVALID_QUEUE_PROPERTIES` if the device does not
+    /// support out-of-order execution and it was requested.
+    pub fn create_command_queue_with_properties(&self, device: &Device,
+                                                properties: QueueProperties)
+                                                -> Result<CommandQueue, Error>
+    {
+        if properties.out_of_order && !try!(device.queue_properties()).out_of_order {
+            return Err(Error::new(CL_INVALID_QUEUE_PROPERTIES as cl_int, "clCreateCommandQueue",
+                                  "The device does not support out-of-order execution"));
+        }
+
+        unsafe
+        {
+            let mut errcode = 0;
+
+            let cqueue = clCreateCommandQueue(self.ctx,
+                                              device.id,
+                                              properties.to_cl(),
+                                              (&mut errcode));
+
+            try!(check_status(errcode, "clCreateCommandQueue", "Failed to create command queue!"));
+
+            Ok(CommandQueue {
+                cqueue: cqueue,
+                state: self.state.clone(),
+            })
+        }
+    }
+
+    /// One command queue per device of the context, in the order of
+    /// `devices()`. The queues share the context's buffers and programs.
+    pub fn create_command_queues(&self) -> Result<Vec<CommandQueue>, Error>
+    {
+        let devices = try!(self.devices());
+        let mut queues = Vec::with_capacity(devices.len());
+        for device in devices.iter() {
+            queues.push(try!(self.create_command_queue(device)));
+        }
+        Ok(queues)
+    }
+
+    /// Creates an event whose completion is controlled by the host, to make
+    /// commands wait for host-side work.
+    pub fn create_user_event(&self) -> Result<UserEvent, Error>
+    {
+        let mut status = CL_SUCCESS as cl_int;
+        let event = unsafe { clCreateUserEvent(self.ctx, &mut status) };
+        try!(check_status(status, "clCreateUserEvent", "Could not create user event"));
+        Ok(UserEvent {
+            event: Event { event: event },
+            set: AtomicBool::new(false),
+        })
+    }
+
+    pub fn create_program_from_source(&self, src: &str) -> Result<Program, Error>
+    {
+        unsafe
+        {
+            let src = match CString::new(src) {
+                Ok(src) => src,
+                Err(_) => return Err(Error::new(CL_INVALID_VALUE as cl_int,
+                                                "clCreateProgramWithSource",
+                                                "Program source contains a NUL byte"))
+            };
+
+            let mut status = CL_SUCCESS as cl_int;
+            let program = clCreateProgramWithSource(
+                self.ctx,
+                1,
+                &src.as_ptr(),
+                ptr::null(),
+                (&mut status));
+            try!(check_status(status, "clCreateProgramWithSource", "Could not create program"));
+
+            Ok(Program { prg: program })
+        }
+    }
+
+    pub fn create_program_from_binary(&self, bin: &str, device: &Device) -> Result<Program, Error> {
+        let src = match CString::new(bin) {
+            Ok(src) => src,
+            Err(_) => return Err(Error::new(CL_INVALID_VALUE as cl_int,
+                                            "clCreateProgramWithBinary",
+                                            "Program binary contains a NUL byte"))
+        };
+        let mut status = CL_SUCCESS as cl_int;
+        let len = bin.len() as libc::size_t;
+        let program = unsafe {
+            clCreateProgramWithBinary(
+                self.ctx,
+                1,
+                &device.id,
+                (&len),
+                (src.as_ptr() as *const *const i8) as *const *const libc::c_uchar,
+                ptr::null_mut(),
+                (&mut status))
+        };
+        try!(check_status(status, "clCreateProgramWithBinary", "Could not create program"));
+
+        Ok(Program {prg: program})
+    }
+}
+
+impl Drop for Context
+{
+    fn drop(&mut self) {
+        unsafe {
+            clReleaseContext(self.ctx);
+        }
+    }
+}
+
+impl<'r, T> KernelArg for &'r (Buffer<T> + 'r) {
+    fn get_value(&self) -> (libc::size_t, *const libc::c_void)
+    {
+        unsafe {
+            (mem::size_of::<cl_mem>() as libc::size_t,
+             self.id_ptr() as *const libc::c_void)
+        }
+    }
+}
+
+impl<'r, T> KernelArg for Box<Buffer<T> + 'r> {
+    fn get_value(&self) -> (libc::size_t, *const libc::c_void)
+    {
+        unsafe {
+            (mem::size_of::<cl_mem>() as libc::size_t,
+             self.id_ptr() as *const libc::c_void)
+        }
+    }
+}
+
+
+/// Holds the closure of a native kernel until either the kernel runs or its
+/// command terminates without running it.
+type NativeSlot<F> = Arc<Mutex<Option<F>>>;
+
+/// `user_func` for `CommandQueue::enqueue_native`; unpacks the argument
+/// block and calls the closure.
+extern fn native_kernel<T, F>(args: *mut libc::c_void)
+    where F: FnOnce(&mut [&mut [T]])
+{
+    unsafe {
+        let args = args as *const usize;
+        let slot = *Box::from_raw(*args as *mut NativeSlot<F>);
+        let f = match slot.lock() {
+            Ok(mut f) => f.take(),
+            Err(_) => None
+        };
+        if let Some(f) = f {
+            let count = *args.offset(1);
+            let mut buffers: Vec<&mut [T]> = (0..count as isize).map(|i| {
+                let data = *args.offset(2 + 2 * i) as *mut T;
+                let len = *args.offset(3 + 2 * i);
+                slice::from_raw_parts_mut(data, len)
+            }).collect();
+            guard_callback("a native kernel", move || f(&mut buffers));
+        }
+    }
+}
+
+pub struct CommandQueue {
+    pub cqueue: cl_command_queue,
+    state: Arc<ContextState>,
+}
+
+unsafe impl Sync for CommandQueue {}
+unsafe impl Send for CommandQueue {}
+
+impl CommandQueue
+{
+    fn info<T: Copy>(&self, name: cl_command_queue_info) -> Result<T, Error>
+    {
+        get_info("clGetCommandQueueInfo", name, |size, value, size_ret| unsafe {
+            clGetCommandQueueInfo(self.cqueue, name, size, value, size_ret)
+        })
+    }
+
+    /// The device commands are executed on.
+    pub fn device(&self) -> Result<Device, Error>
+    {
+        self.info::<cl_device_id>(CL_QUEUE_DEVICE).map(|id| Device { id: id })
+    }
+
+    pub fn context(&self) -> Result<Context, Error>
+    {
+        let ctx = try!(self.info::<cl_context>(CL_QUEUE_CONTEXT));
+        Ok(unsafe { Context::retain(ctx) })
+    }
+
+    pub fn properties(&self) -> Result<QueueProperties, Error>
+    {
+        self.info::<cl_command_queue_properties>(CL_QUEUE_PROPERTIES)
+            .map(QueueProperties::from_cl)
+    }
+
+    /// An event that completes once every command enqueued before it has.
+    pub fn marker(&self) -> Result<Event, Error>
+    {
+        let mut e: cl_event = ptr::null_mut();
+        let status = unsafe { clEnqueueMarker(self.cqueue, &mut e) };
+        try!(check_status(status, "clEnqueueMarker", "Error enqueuing marker."));
+        Ok(Event { event: e })
+    }
+
+    /// Commands enqueued after the barrier only start once every command
+    /// enqueued before it has completed.
+    pub fn barrier(&self) -> Result<(), Error>
+    {
+        let status = unsafe { clEnqueueBarrier(self.cqueue) };
+        check_status(status, "clEnqueueBarrier", "Error enqueuing barrier.")
+    }
+
+    /// Commands enqueued after this only start once the events in `events`
+    /// have completed. Does nothing for an empty list.
+    pub fn wait_for<E: EventList>(&self, events: E) -> Result<(), Error>
+    {
+        events.as_event_list(|event_list, event_list_length| {
+            if event_list_length == 0 {
+                return Ok(());
+            }
+            let status = unsafe {
+                clEnqueueWaitForEvents(self.cqueue, event_list_length, event_list)
+            };
+            check_status(status, "clEnqueueWaitForEvents", "Error enqueuing wait for events.")
+        })
+    }
+
+    /// Submits the enqueued commands to the device without waiting for them.
+    pub fn flush(&self) -> Result<(), Error>
+    {
+        let status = unsafe { clFlush(self.cqueue) };
+        check_status(status, "clFlush", "Error flushing command queue.")
+    }
+
+    /// Blocks until every enqueued command has completed.
+    pub fn finish(&self) -> Result<(), Error>
+    {
+        let status = unsafe { clFinish(self.cqueue) };
+        check_status(status, "clFinish", "Error finishing command queue.")
+    }
+
+    //synchronous
+    pub fn enqueue_kernel<I: KernelIndex, E: EventList>(&self, k: &Kernel, global: I, local: Option<I>, wait_on: E)
+        -> Result<Event, Error>
+    {
+        let event = try!(self.enqueue_async_kernel(k, global, local, wait_on));
+        let status = unsafe { clFinish(self.cqueue) };
+        try!(check_status(status, "clFinish", "Error finishing kernel."));
+        Ok(event)
+    }
+
+    /// Runs `k` over `range` and waits for it to complete.
+    pub fn enqueue_ndrange<R: Into<NDRange>, E: EventList>(&self, k: &Kernel, range: R, wait_on: E)
+        -> Result<Event, Error>
+    {
+        let event = try!(self.enqueue_async_ndrange(k, range, wait_on));
+        try!(event.wait());
+        Ok(event)
+    }
+
+    /// Enqueues `k` over `range`, after checking the range against the
+    /// queue's device.
+    pub fn enqueue_async_ndrange<R: Into<NDRange>, E: EventList>(&self, k: &Kernel, range: R,
+                                                                 wait_on: E)
+        -> Result<Event, Error>
+    {
+        let range = range.into();
+        try!(range.validate(&try!(self.device())));
+
+        wait_on.as_event_list(|event_list, event_list_length| {
+            let mut e: cl_event = ptr::null_mut();
+            let status = unsafe {
+                clEnqueueNDRangeKernel(
+                    self.cqueue,
+                    k.kernel,
+                    range.dimensions() as cl_uint,
+                    range.offset.as_ptr() as *const libc::size_t,
+                    range.global.as_ptr() as *const libc::size_t,
+                    match range.local {
+                        Some(ref l) => l.as_ptr() as *const libc::size_t,
+                        None => ptr::null()
+                    },
+                    event_list_length,
+                    event_list,
+                    (&mut e))
+            };
+            try!(check_status(status, "clEnqueueNDRangeKernel", "Error enqueuing kernel."));
+            Ok(Event { event: e })
+        })
+    }
+
+    //asynchronous
+    /// Like `enqueue_async_ndrange` over `global` and `local`, starting at
+    /// zero.
+    pub fn enqueue_async_kernel<I: KernelIndex, E: EventList>(&self, k: &Kernel, global: I, local: Option<I>, wait_on: E)
+        -> Result<Event, Error>
+    {
+        let mut range = NDRange::new(&global.sizes());
+        range.local = local.map(|l| l.sizes());
+        self.enqueue_async_ndrange(k, range, wait_on)
+    }
+
+    /// Runs `k` as a single work-item.
+    pub fn enqueue_task<E: EventList>(&self, k: &Kernel, wait_on: E) -> Result<Event, Error>
+    {
+        wait_on.as_event_list(|event_list, event_list_length| {
+            let mut e: cl_event = ptr::null_mut();
+            let status = unsafe {
+                clEnqueueTask(self.cqueue, k.kernel, event_list_length, event_list, &mut e)
+            };
+            try!(check_status(status, "clEnqueueTask", "Error enqueuing task."));
+            Ok(Event { event: e })
+        })
+    }
+
+    /// Runs `f` on the host as a command of this queue, which must belong to
+    /// a device reporting `CL_EXEC_NATIVE_KERNEL`. `f` receives the contents
+    /// of `buffers`, in order; each buffer may be passed only once. A panic
+    /// in `f` is logged and otherwise ignored. If the command terminates
+    /// without running, `f` is dropped. Failing to arrange that is an error
+    /// even though the command has been enqueued.
+    pub fn enqueue_native<T, F, E>(&self, buffers: &[&CLBuffer<T>], f: F, wait_on: E)
+        -> Result<Event, Error>
+        where F: FnOnce(&mut [&mut [T]]) + Send + 'static, E: EventList
+    {
+        let api = "clEnqueueNativeKernel";
+        if !try!(try!(self.device()).execution_capabilities()).native_kernel {
+            return Err(Error::new(CL_INVALID_OPERATION as cl_int, api,
+                                  "The device cannot execute native kernels"));
+        }
+        let ids: Vec<cl_mem> = buffers.iter().map(|b| b.id()).collect();
+        for (i, id) in ids.iter().enumerate() {
+            if ids[..i].contains(id) {
+                return Err(Error::new(CL_INVALID_VALUE as cl_int, api,
+                                      &format!("Buffer {} is passed more than once", i)));
+            }
+        }
 
-                    check(err, "Failed to read buffer");
+        // The argument block is [closure, count, (buffer, len)*]; OpenCL
+        // copies it and replaces each buffer handle by its host pointer.
+        let mut args: Vec<usize> = vec![0, buffers.len()];
+        for (buf, id) in buffers.iter().zip(ids.iter()) {
+            args.push(*id as usize);
+            args.push(try!(buf.len()));
+        }
+        let locations: Vec<*const libc::c_void> = (0..buffers.len()).map(|i| {
+            (&args[2 + 2 * i] as *const usize) as *const libc::c_void
+        }).collect();
+        let slot: NativeSlot<F> = Arc::new(Mutex::new(Some(f)));
+        let user_data = Box::into_raw(Box::new(slot.clone()));
+        args[0] = user_data as usize;
+
+        wait_on.as_event_list(|event_list, event_list_length| {
+            let mut e: cl_event = ptr::null_mut();
+            let status = unsafe {
+                clEnqueueNativeKernel(self.cqueue,
+                                      native_kernel::<T, F>,
+                                      args.as_ptr() as *mut libc::c_void,
+                                      (args.len() * mem::size_of::<usize>()) as libc::size_t,
+                                      ids.len() as cl_uint,
+                                      ids.as_ptr(),
+                                      locations.as_ptr(),
+                                      event_list_length,
+                                      event_list,
+                                      &mut e)
+            };
+            // `native_kernel` frees `user_data` when it runs. A rejected
+            // command never runs, so the box is ours to free.
+            if let Err(err) = check_status(status, api, "Error enqueuing native kernel.") {
+                drop(unsafe { Box::from_raw(user_data) });
+                return Err(err);
+            }
+
+            // From here on `user_data` belongs to `native_kernel`, and only
+            // the box leaks if the command never runs: the callback drops
+            // the closure it holds.
+            let event = Event { event: e };
+            try!(event.on_complete(move |_| {
+                if let Ok(mut f) = slot.lock() {
+                    f.take();
                 }
+            }));
+            Ok(event)
+        })
+    }
+
+    pub fn get<T, U, B: Buffer<T>, G: Get<B, U>, E: EventList>(&self, buf: &B, event: E) -> Result<G, Error>
+    {
+        event.as_event_list(|event_list, event_list_length| {
+            Get::get(buf, |offset, ptr, len| {
+                let err = unsafe {
+                    clEnqueueReadBuffer(self.cqueue,
+                                        buf.id(),
+                                        CL_TRUE,
+                                        offset as libc::size_t,
+                                        len,
+                                        ptr,
+                                        event_list_length,
+                                        event_list,
+                                        ptr::null_mut())
+                };
+                check_status(err, "clEnqueueReadBuffer", "Failed to read buffer")
             })
         })
     }
 
     pub fn write<U: Write, T, E: EventList, B: Buffer<T>>(&self, mem: &B, write: &U, event: E)
+        -> Result<(), Error>
     {
+        let mut err = CL_SUCCESS as cl_int;
         unsafe {
             event.as_event_list(|event_list, event_list_length| {
                 write.write(|offset, p, len| {
-                    let err = clEnqueueWriteBuffer(self.cqueue,
+                    err = clEnqueueWriteBuffer(self.cqueue,
                                                    mem.id(),
                                                    CL_TRUE,
                                                    offset as libc::size_t,
@@ -501,56 +2075,622 @@ impl CommandQueue
                                                    event_list_length,
                                                    event_list,
                                                    ptr::null_mut());
+                })
+            })
+        }
+        check_status(err, "clEnqueueWriteBuffer", "Failed to write buffer")
+    }
 
-                    check(err, "Failed to write buffer");
+    /// Starts copying `write` into `mem` without waiting. The returned
+    /// transfer owns `write` until the copy has completed.
+    pub fn write_async<U: Write + 'static, T, E: EventList, B: Buffer<T>>(&self, mem: &B, write: U,
+                                                                         event: E)
+        -> Result<Transfer<U>, Error>
+    {
+        // Boxed so the memory handed to OpenCL stays put when the transfer
+        // is moved.
+        let write = Box::new(write);
+        let mut out_event = ptr::null_mut();
+        let mut err = CL_SUCCESS as cl_int;
+        unsafe {
+            event.as_event_list(|evt, evt_len| {
+                write.write(|offset, p, len| {
+                    err = clEnqueueWriteBuffer(self.cqueue,
+                                                   mem.id(),
+                                                   CL_FALSE,
+                                                   offset as libc::size_t,
+                                                   len as libc::size_t,
+                                                   p as *const libc::c_void,
+                                                   evt_len,
+                                                   evt,
+                                                   &mut out_event);
                 })
             })
         }
+        try!(check_status(err, "clEnqueueWriteBuffer", "Failed to write buffer"));
+        Ok(Transfer { data: Some(write), event: Event { event: out_event } })
+    }
+
+    pub fn read<T, U: Read, E: EventList, B: Buffer<T>>(&self, mem: &B, read: &mut U, event: E)
+        -> Result<(), Error>
+    {
+        let mut err = CL_SUCCESS as cl_int;
+        event.as_event_list(|event_list, event_list_length| {
+                read.read(|offset, p, len| {
+                        unsafe {
+                            err = clEnqueueReadBuffer(self.cqueue,
+                                                          mem.id(),
+                                                          CL_TRUE,
+                                                          offset as libc::size_t,
+                                                          len as libc::size_t,
+                                                          p as *mut libc::c_void,
+                                                          event_list_length,
+                                                          event_list,
+                                                          ptr::null_mut());
+                        }
+                    })
+            });
+        check_status(err, "clEnqueueReadBuffer", "Failed to read buffer")
+    }
+
+    /// Starts copying `mem` into `read` without waiting. The returned
+    /// transfer owns `read` and hands it back, filled, from `wait()`.
+    pub fn read_async<T, U: Read + 'static, E: EventList, B: Buffer<T>>(&self, mem: &B, read: U,
+                                                                       event: E)
+        -> Result<Transfer<U>, Error>
+    {
+        // Boxed so the memory handed to OpenCL stays put when the transfer
+        // is moved.
+        let mut read = Box::new(read);
+        let mut out_event = ptr::null_mut();
+        let mut err = CL_SUCCESS as cl_int;
+        event.as_event_list(|event_list, event_list_length| {
+            read.read(|offset, p, len| {
+                unsafe {
+                    err = clEnqueueReadBuffer(self.cqueue,
+                                              mem.id(),
+                                              CL_FALSE,
+                                              offset as libc::size_t,
+                                              len as libc::size_t,
+                                              p as *mut libc::c_void,
+                                              event_list_length,
+                                              event_list,
+                                              &mut out_event);
+                }
+            })
+        });
+        try!(check_status(err, "clEnqueueReadBuffer", "Failed to read buffer"));
+        Ok(Transfer { data: Some(read), event: Event { event: out_event } })
+    }
+
+    /// Maps the elements `range` of `buf` into host memory for reading,
+    /// blocking until they are available. A buffer can be mapped for
+    /// reading several times at once.
+    pub fn map_read<'a, T>(&'a self, buf: &'a CLBuffer<T>, range: Range<usize>)
+        -> Result<MappedRead<'a, T>, Error>
+    {
+        let mapping = try!(self.map_region(buf, CL_MAP_READ, range));
+        Ok(MappedRead { mapping: mapping })
+    }
+
+    /// Maps the elements `range` of `buf` into host memory for reading and
+    /// writing, blocking until they are available. The buffer stays
+    /// borrowed until the mapping is gone.
+    pub fn map<'a, T>(&'a self, buf: &'a mut CLBuffer<T>, range: Range<usize>)
+        -> Result<MappedBuffer<'a, T>, Error>
+    {
+        let mapping = try!(self.map_region(buf, CL_MAP_READ | CL_MAP_WRITE, range));
+        Ok(MappedBuffer { mapping: mapping })
+    }
+
+    /// Maps the elements `range` of `buf` for writing only. Their contents
+    /// are undefined until written, so the mapping cannot be read. The
+    /// buffer stays borrowed until the mapping is gone.
+    pub fn map_write<'a, T>(&'a self, buf: &'a mut CLBuffer<T>, range: Range<usize>)
+        -> Result<MappedWrite<'a, T>, Error>
+    {
+        let mapping = try!(self.map_region(buf, CL_MAP_WRITE, range));
+        Ok(MappedWrite { mapping: mapping })
+    }
+
+    fn map_region<'a, T>(&'a self, buf: &'a CLBuffer<T>, flags: cl_map_flags,
+                         range: Range<usize>)
+        -> Result<Mapping<'a, T>, Error>
+    {
+        let api = "clEnqueueMapBuffer";
+        let size = mem::size_of::<T>();
+        let len = try!(buf.len());
+        if range.start > range.end || range.end > len {
+            return Err(Error::new(CL_INVALID_VALUE as cl_int, api,
+                                  &format!("Range {:?} is out of bounds of the buffer of {} \
+                                            elements", range, len)));
+        }
+        let mut status = CL_SUCCESS as cl_int;
+        let ptr = unsafe {
+            clEnqueueMapBuffer(self.cqueue,
+                               buf.id(),
+                               CL_TRUE,
+                               flags,
+                               (range.start * size) as libc::size_t,
+                               ((range.end - range.start) * size) as libc::size_t,
+                               0,
+                               ptr::null(),
+                               ptr::null_mut(),
+                               &mut status)
+        };
+        try!(check_status(status, api, "Failed to map buffer"));
+        Ok(Mapping {
+            queue: self,
+            buffer: buf,
+            ptr: ptr as *mut T,
+            len: range.end - range.start,
+            mapped: true,
+        })
+    }
+
+    /// Copies the elements `src_range` of `src` to `dst`, starting at
+    /// element `dst_offset`, on the device. An empty range copies nothing;
+    /// its event is a marker enqueued after `wait_on`.
+    pub fn copy<T, S: Buffer<T>, D: Buffer<T>, E: EventList>(&self, src: &S, src_range: Range<usize>,
+                                                             dst: &D, dst_offset: usize, wait_on: E)
+        -> Result<Event, Error>
+    {
+        let api = "clEnqueueCopyBuffer";
+        let size = mem::size_of::<T>();
+        let src_len = try!(src.len());
+        let dst_len = try!(dst.len());
+        if src_range.start > src_range.end || src_range.end > src_len {
+            return Err(Error::new(CL_INVALID_VALUE as cl_int, api,
+                                  &format!("Range {:?} is out of bounds of the source buffer \
+                                            of {} elements", src_range, src_len)));
+        }
+        let count = src_range.end - src_range.start;
+        if dst_offset.checked_add(count).map_or(true, |end| end > dst_len) {
+            return Err(Error::new(CL_INVALID_VALUE as cl_int, api,
+                                  &format!("{} elements at offset {} are out of bounds of the \
+                                            destination buffer of {} elements",
+                                           count, dst_offset, dst_len)));
+        }
+        if src.id() == dst.id() &&
+           src_range.start < dst_offset + count && dst_offset < src_range.end {
+            return Err(Error::new(CL_MEM_COPY_OVERLAP as cl_int, api,
+                                  "Source and destination ranges overlap"));
+        }
+        if count == 0 {
+            try!(self.wait_for(wait_on));
+            return self.marker();
+        }
+
+        wait_on.as_event_list(|event_list, event_list_length| {
+            let mut e: cl_event = ptr::null_mut();
+            let status = unsafe {
+                clEnqueueCopyBuffer(self.cqueue,
+                                    src.id(),
+                                    dst.id(),
+                                    (src_range.start * size) as libc::size_t,
+                                    (dst_offset * size) as libc::size_t,
+                                    (count * size) as libc::size_t,
+                                    event_list_length,
+                                    event_list,
+                                    &mut e)
+            };
+            try!(check_status(status, api, "Failed to copy buffer"));
+            Ok(Event { event: e })
+        })
+    }
+
+    /// Sets the elements `range` of `buf` to `pattern`. Uses
+    /// `clEnqueueFillBuffer` on OpenCL 1.2; otherwise, or if the size of `T`
+    /// is not a power of two up to 128 bytes, a fill kernel is enqueued
+    /// instead. The kernel is built once per device and serves every `T`.
+    /// An empty range fills nothing; its event is a marker, like that of an
+    /// empty `copy`.
+    pub fn fill<T: Copy, B: Buffer<T>>(&self, buf: &B, pattern: T, range: Range<usize>)
+        -> Result<Event, Error>
+    {
+        let api = "clEnqueueFillBuffer";
+        let size = mem::size_of::<T>();
+        let len = try!(buf.len());
+        if range.start > range.end || range.end > len {
+            return Err(Error::new(CL_INVALID_VALUE as cl_int, api,
+                                  &format!("Range {:?} is out of bounds of the buffer of {} \
+                                            elements", range, len)));
+        }
+        if range.start == range.end {
+            return self.marker();
+        }
+
+        let device = try!(self.device());
+        let core = ClVersion::new(1, 2);
+        if size.is_power_of_two() && size <= 128 &&
+           try!(device.cl_version()) >= core && try!(try!(device.platform()).cl_version()) >= core {
+            let enqueue_fill = try!(cl_1_2::enqueue_fill_buffer().map_err(|e| {
+                Error::new(CL_INVALID_OPERATION as cl_int, "dlsym", &e)
+            }));
+            let mut e: cl_event = ptr::null_mut();
+            let status = enqueue_fill(
+                self.cqueue,
+                buf.id(),
+                (&pattern as *const T) as *const libc::c_void,
+                size as libc::size_t,
+                (range.start * size) as libc::size_t,
+                ((range.end - range.start) * size) as libc::size_t,
+                0,
+                ptr::null(),
+                &mut e);
+            try!(check_status(status, api, "Failed to fill buffer"));
+            return Ok(Event { event: e });
+        }
+
+        // The kernel fills byte by byte so that any `T` works; the pattern
+        // buffer is released once the fill completes.
+        let ctx = try!(self.context());
+        // A panic while the lock was held cannot leave the cache inconsistent,
+        // as every argument is set again below.
+        let mut kernels = self.state.fill_kernels.lock().unwrap_or_else(|e| e.into_inner());
+        if !kernels.contains_key(&device) {
+            let src = "__kernel void fill(__global uchar *dst, __constant uchar *pattern, \
+                                          uint pattern_size) { \
+                           size_t i = get_global_id(0); \
+                           dst[i] = pattern[i % pattern_size]; \
+                       }";
+            let program = try!(ctx.create_program_from_source(src));
+            try!(program.build(&device).map_err(|e| e.error));
+            kernels.insert(device, try!(program.create_kernel("fill")));
+        }
+        let kernel = &kernels[&device];
+        let bytes = unsafe {
+            slice::from_raw_parts((&pattern as *const T) as *const u8, size).to_vec()
+        };
+        let pattern: CLBuffer<u8> = try!(ctx.create_buffer_from(bytes, CL_MEM_READ_ONLY));
+        let dst: &Buffer<T> = buf;
+        try!(kernel.set_arg(0, &dst));
+        try!(kernel.set_arg(1, &pattern));
+        try!(kernel.set_arg(2, &(size as u32)));
+        let range = NDRange::new(&[(range.end - range.start) * size]).offset(&[range.start * size]);
+        self.enqueue_async_ndrange(&kernel, range, ())
+    }
+
+    /// Copies the box of extent `region` at `src_origin` in `src` to
+    /// `dst_origin` in `dst`, on the device. Origins and extents are
+    /// `[x, y, z]` in elements.
+    pub fn copy_rect<T, S, D, E>(&self, src: &S, src_origin: [usize; 3],
+                                 dst: &D, dst_origin: [usize; 3],
+                                 region: [usize; 3], wait_on: E)
+        -> Result<Event, Error>
+        where S: Buffer<T> + Dimensions, D: Buffer<T> + Dimensions, E: EventList
+    {
+        let api = "clEnqueueCopyBufferRect";
+        try!(check_region(api, "source", src.dimensions(), src_origin, region));
+        try!(check_region(api, "destination", dst.dimensions(), dst_origin, region));
+        if src.id() == dst.id() &&
+           (0..3).all(|i| src_origin[i] < dst_origin[i] + region[i] &&
+                          dst_origin[i] < src_origin[i] + region[i]) {
+            return Err(Error::new(CL_MEM_COPY_OVERLAP as cl_int, api,
+                                  "Source and destination regions overlap"));
+        }
+        let (src_row, src_slice) = rect_pitches::<T>(src.dimensions());
+        let (dst_row, dst_slice) = rect_pitches::<T>(dst.dimensions());
+
+        wait_on.as_event_list(|event_list, event_list_length| {
+            let mut e: cl_event = ptr::null_mut();
+            let status = unsafe {
+                clEnqueueCopyBufferRect(self.cqueue,
+                                        src.id(),
+                                        dst.id(),
+                                        rect_bytes::<T>(src_origin).as_ptr(),
+                                        rect_bytes::<T>(dst_origin).as_ptr(),
+                                        rect_bytes::<T>(region).as_ptr(),
+                                        src_row, src_slice,
+                                        dst_row, dst_slice,
+                                        event_list_length,
+                                        event_list,
+                                        &mut e)
+            };
+            try!(check_status(status, api, "Failed to copy buffer region"));
+            Ok(Event { event: e })
+        })
+    }
+
+    /// Reads the box of extent `region` at `src_origin` in `src` into
+    /// `dst_origin` in `dst`, blocking until done.
+    pub fn read_rect<T, B, U, E>(&self, src: &B, src_origin: [usize; 3],
+                                 dst: &mut U, dst_origin: [usize; 3],
+                                 region: [usize; 3], wait_on: E)
+        -> Result<Event, Error>
+        where B: Buffer<T> + Dimensions, U: Read + Dimensions, E: EventList
+    {
+        let api = "clEnqueueReadBufferRect";
+        let host_dims = dst.dimensions();
+        try!(check_region(api, "buffer", src.dimensions(), src_origin, region));
+        try!(check_region(api, "host array", host_dims, dst_origin, region));
+        let (buf_row, buf_slice) = rect_pitches::<T>(src.dimensions());
+        let (host_row, host_slice) = rect_pitches::<T>(host_dims);
+        let host_bytes = (host_slice as usize) * host_dims[2];
+
+        let mut result = Err(Error::new(CL_INVALID_VALUE as cl_int, api,
+                                        "Host array provided no memory"));
+        wait_on.as_event_list(|event_list, event_list_length| {
+            dst.read(|offset, p, len| {
+                if offset != 0 || (len as usize) < host_bytes {
+                    result = Err(Error::new(CL_INVALID_VALUE as cl_int, api,
+                                            "Host array is smaller than its dimensions"));
+                    return;
+                }
+                let mut e: cl_event = ptr::null_mut();
+                let status = unsafe {
+                    clEnqueueReadBufferRect(self.cqueue,
+                                            src.id(),
+                                            CL_TRUE,
+                                            rect_bytes::<T>(src_origin).as_ptr(),
+                                            rect_bytes::<T>(dst_origin).as_ptr(),
+                                            rect_bytes::<T>(region).as_ptr(),
+                                            buf_row, buf_slice,
+                                            host_row, host_slice,
+                                            p,
+                                            event_list_length,
+                                            event_list,
+                                            &mut e)
+                };
+                result = check_status(status, api, "Failed to read buffer region")
+                    .map(|_| Event { event: e });
+            })
+        });
+        result
+    }
+
+    /// Writes the box of extent `region` at `src_origin` in `src` to
+    /// `dst_origin` in `dst`, blocking until done.
+    pub fn write_rect<T, U, B, E>(&self, src: &U, src_origin: [usize; 3],
+                                  dst: &B, dst_origin: [usize; 3],
+                                  region: [usize; 3], wait_on: E)
+        -> Result<Event, Error>
+        where U: Write + Dimensions, B: Buffer<T> + Dimensions, E: EventList
+    {
+        let api = "clEnqueueWriteBufferRect";
+        let host_dims = src.dimensions();
+        try!(check_region(api, "host array", host_dims, src_origin, region));
+        try!(check_region(api, "buffer", dst.dimensions(), dst_origin, region));
+        let (buf_row, buf_slice) = rect_pitches::<T>(dst.dimensions());
+        let (host_row, host_slice) = rect_pitches::<T>(host_dims);
+        let host_bytes = (host_slice as usize) * host_dims[2];
+
+        let mut result = Err(Error::new(CL_INVALID_VALUE as cl_int, api,
+                                        "Host array provided no memory"));
+        wait_on.as_event_list(|event_list, event_list_length| {
+            src.write(|offset, p, len| {
+                if offset != 0 || (len as usize) < host_bytes {
+                    result = Err(Error::new(CL_INVALID_VALUE as cl_int, api,
+                                            "Host array is smaller than its dimensions"));
+                    return;
+                }
+                let mut e: cl_event = ptr::null_mut();
+                let status = unsafe {
+                    clEnqueueWriteBufferRect(self.cqueue,
+                                             dst.id(),
+                                             CL_TRUE,
+                                             rect_bytes::<T>(dst_origin).as_ptr(),
+                                             rect_bytes::<T>(src_origin).as_ptr(),
+                                             rect_bytes::<T>(region).as_ptr(),
+                                             buf_row, buf_slice,
+                                             host_row, host_slice,
+                                             p,
+                                             event_list_length,
+                                             event_list,
+                                             &mut e)
+                };
+                result = check_status(status, api, "Failed to write buffer region")
+                    .map(|_| Event { event: e });
+            })
+        });
+        result
+    }
+}
+
+/// Checks that the box at `origin` of extent `region` lies within an array
+/// of extent `dims`.
+fn check_region(api: &'static str, what: &str, dims: [usize; 3], origin: [usize; 3],
+                region: [usize; 3]) -> Result<(), Error>
+{
+    for i in 0..3 {
+        let end = origin[i].checked_add(region[i]);
+        if region[i] == 0 || end.map_or(true, |end| end > dims[i]) {
+            return Err(Error::new(CL_INVALID_VALUE as cl_int, api,
+                                  &format!("Region at {:?} of size {:?} does not fit the {} \
+                                            of size {:?}", origin, region, what, dims)));
+        }
+    }
+    Ok(())
+}
+
+/// `[x, y, z]` in elements to the byte-based form the rect functions take.
+fn rect_bytes<T>(v: [usize; 3]) -> [libc::size_t; 3]
+{
+    [(v[0] * mem::size_of::<T>()) as libc::size_t, v[1] as libc::size_t, v[2] as libc::size_t]
+}
+
+/// Row and slice pitch in bytes of an array of extent `dims`.
+fn rect_pitches<T>(dims: [usize; 3]) -> (libc::size_t, libc::size_t)
+{
+    let row = dims[0] * mem::size_of::<T>();
+    (row as libc::size_t, (row * dims[1]) as libc::size_t)
+}
+
+/// A mapped region of a buffer, unmapped when dropped.
+struct Mapping<'a, T: 'a> {
+    queue: &'a CommandQueue,
+    buffer: &'a CLBuffer<T>,
+    ptr: *mut T,
+    len: usize,
+    mapped: bool,
+}
+
+impl<'a, T> Mapping<'a, T> {
+    fn enqueue_unmap(&mut self) -> Result<Event, Error>
+    {
+        self.mapped = false;
+        let mut e: cl_event = ptr::null_mut();
+        let status = unsafe {
+            clEnqueueUnmapMemObject(self.queue.cqueue,
+                                    self.buffer.id(),
+                                    self.ptr as *mut libc::c_void,
+                                    0,
+                                    ptr::null(),
+                                    &mut e)
+        };
+        try!(check_status(status, "clEnqueueUnmapMemObject", "Failed to unmap buffer"));
+        Ok(Event { event: e })
+    }
+}
+
+impl<'a, T> Drop for Mapping<'a, T> {
+    fn drop(&mut self) {
+        if self.mapped {
+            if let Err(e) = self.enqueue_unmap().and_then(|event| event.wait()) {
+                error!("{}", e);
+            }
+        }
+    }
+}
+
+/// A region of a buffer mapped for reading by `CommandQueue::map_read`.
+/// Dropping it unmaps the region and waits for that to complete.
+pub struct MappedRead<'a, T: 'a> {
+    mapping: Mapping<'a, T>,
+}
+
+impl<'a, T> MappedRead<'a, T> {
+    /// Unmaps the region without waiting. Commands writing to the buffer
+    /// should wait on the returned event if the queue runs out of order.
+    pub fn unmap(mut self) -> Result<Event, Error>
+    {
+        self.mapping.enqueue_unmap()
+    }
+}
+
+impl<'a, T> Deref for MappedRead<'a, T> {
+    type Target = [T];
+
+    fn deref(&self) -> &[T]
+    {
+        unsafe { slice::from_raw_parts(self.mapping.ptr, self.mapping.len) }
+    }
+}
+
+/// A region of a buffer mapped for reading and writing by
+/// `CommandQueue::map`. Dropping it unmaps the region and waits for that to
+/// complete.
+pub struct MappedBuffer<'a, T: 'a> {
+    mapping: Mapping<'a, T>,
+}
+
+impl<'a, T> MappedBuffer<'a, T> {
+    /// Unmaps the region without waiting. Commands using the buffer should
+    /// wait on the returned event if the queue runs out of order.
+    pub fn unmap(mut self) -> Result<Event, Error>
+    {
+        self.mapping.enqueue_unmap()
+    }
+}
+
+impl<'a, T> Deref for MappedBuffer<'a, T> {
+    type Target = [T];
+
+    fn deref(&self) -> &[T]
+    {
+        unsafe { slice::from_raw_parts(self.mapping.ptr, self.mapping.len) }
+    }
+}
+
+impl<'a, T> DerefMut for MappedBuffer<'a, T> {
+    fn deref_mut(&mut self) -> &mut [T]
+    {
+        unsafe { slice::from_raw_parts_mut(self.mapping.ptr, self.mapping.len) }
+    }
+}
+
+/// A region of a buffer mapped for writing by `CommandQueue::map_write`.
+/// Its contents are undefined until written, so it can only be written to.
+/// Dropping it unmaps the region and waits for that to complete.
+pub struct MappedWrite<'a, T: 'a> {
+    mapping: Mapping<'a, T>,
+}
+
+impl<'a, T> MappedWrite<'a, T> {
+    /// In elements.
+    pub fn len(&self) -> usize
+    {
+        self.mapping.len
+    }
+
+    pub fn is_empty(&self) -> bool
+    {
+        self.mapping.len == 0
+    }
+
+    /// The start of the mapped region.
+    pub fn as_mut_ptr(&mut self) -> *mut T
+    {
+        self.mapping.ptr
+    }
+
+    /// Copies `data` into the region, starting at element `offset`.
+    pub fn write(&mut self, offset: usize, data: &[T]) -> Result<(), Error>
+        where T: Copy
+    {
+        if offset.checked_add(data.len()).map_or(true, |end| end > self.mapping.len) {
+            return Err(Error::new(CL_INVALID_VALUE as cl_int, "clEnqueueMapBuffer",
+                                  &format!("{} elements at offset {} are out of bounds of the \
+                                            mapping of {} elements",
+                                           data.len(), offset, self.mapping.len)));
+        }
+        unsafe {
+            ptr::copy_nonoverlapping(data.as_ptr(), self.mapping.ptr.offset(offset as isize),
+                                     data.len());
+        }
+        Ok(())
+    }
+
+    /// Unmaps the region without waiting. Commands using the buffer should
+    /// wait on the returned event if the queue runs out of order.
+    pub fn unmap(mut self) -> Result<Event, Error>
+    {
+        self.mapping.enqueue_unmap()
     }
+}
+
+/// A non-blocking transfer between a buffer and host memory it owns. The
+/// memory is handed back by `wait()` once the transfer has completed;
+/// dropping the transfer waits for it too.
+pub struct Transfer<U> {
+    data: Option<Box<U>>,
+    event: Event,
+}
 
-    pub fn write_async<U: Write, T, E: EventList, B: Buffer<T>>(&self, mem: &B, write: &U, event: E) -> Event
+impl<U> Transfer<U> {
+    /// Completes with the transfer; other commands may wait on it.
+    pub fn event(&self) -> &Event
     {
-        let mut out_event = None;
-        unsafe {
-            event.as_event_list(|evt, evt_len| {
-                write.write(|offset, p, len| {
-                    let mut e: cl_event = ptr::null_mut();
-                    let err = clEnqueueWriteBuffer(self.cqueue,
-                                                   mem.id(),
-                                                   CL_FALSE,
-                                                   offset as libc::size_t,
-                                                   len as libc::size_t,
-                                                   p as *const libc::c_void,
-                                                   evt_len,
-                                                   evt,
-                                                   &mut e);
-                    out_event = Some(e);
-                    check(err, "Failed to write buffer");
-                })
-            })
-        }
-        Event { event: out_event.unwrap() }
+        &self.event
     }
 
-    pub fn read<T, U: Read, E: EventList, B: Buffer<T>>(&self, mem: &B, read: &mut U, event: E)
+    /// Blocks until the transfer has completed and returns the host memory.
+    pub fn wait(mut self) -> Result<U, Error>
     {
-        event.as_event_list(|event_list, event_list_length| {
-                read.read(|offset, p, len| {
-                        unsafe {
-                            let err = clEnqueueReadBuffer(self.cqueue,
-                                                          mem.id(),
-                                                          CL_TRUE,
-                                                          offset as libc::size_t,
-                                                          len as libc::size_t,
-                                                          p as *mut libc::c_void,
-                                                          event_list_length,
-                                                          event_list,
-                                                          ptr::null_mut());
+        try!(self.event.wait());
+        Ok(*self.data.take().unwrap())
+    }
+}
 
-                            check(err, "Failed to read buffer");
-                        }
-                    })
-            })
+impl<U> Drop for Transfer<U> {
+    fn drop(&mut self) {
+        if self.data.is_some() {
+            // OpenCL may still be using the memory; it must not be freed
+            // before the transfer is over.
+            if self.event.wait().is_err() {
+                mem::forget(self.data.take());
+            }
+        }
     }
 }
 
@@ -584,52 +2724,208 @@ impl Drop for Program
     }
 }
 
+/// The value of `CL_PROGRAM_BUILD_STATUS` for one device.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum BuildStatus {
+    Success,
+    None,
+    Error,
+    InProgress,
+}
+
+impl BuildStatus {
+    fn from_cl(status: cl_build_status) -> BuildStatus {
+        match status as cl_uint {
+            s if s == CL_BUILD_SUCCESS => BuildStatus::Success,
+            s if s == CL_BUILD_IN_PROGRESS => BuildStatus::InProgress,
+            s if s == CL_BUILD_ERROR => BuildStatus::Error,
+            _ => BuildStatus::None
+        }
+    }
+}
+
+/// A failed program build.
+#[derive(Clone, Debug)]
+pub struct BuildError {
+    /// What went wrong, usually `CL_BUILD_PROGRAM_FAILURE` from
+    /// `clBuildProgram`.
+    pub error: Error,
+    /// The options the program was built with.
+    pub options: String,
+    /// The build status and log of every device the program was built for.
+    pub devices: HashMap<Device, (BuildStatus, String)>,
+}
+
+impl fmt::Display for BuildError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        try!(write!(f, "{}", self.error));
+        if !self.options.is_empty() {
+            try!(write!(f, " with options \"{}\"", self.options));
+        }
+        for (device, &(status, ref log)) in self.devices.iter() {
+            if status == BuildStatus::Success {
+                continue;
+            }
+            let name = device.name().unwrap_or(format!("{:?}", device.id));
+            try!(write!(f, "\n{} ({:?}):\n{}", name, status, log));
+        }
+        Ok(())
+    }
+}
+
+impl error::Error for BuildError {
+    fn description(&self) -> &str {
+        self.error.message()
+    }
+}
+
 impl Program
 {
     /// Build the program for a given device.
     ///
-    /// Both Ok and Err returns include the build log.
-    pub fn build(&self, device: &Device) -> Result<String, String>
+    /// On success the build log is returned; on failure it is part of the
+    /// `BuildError`.
+    pub fn build(&self, device: &Device) -> Result<String, BuildError>
     {
-        unsafe
-        {
-            let ret = clBuildProgram(self.prg, 1, &device.id,
-                                     ptr::null(),
-                                     mem::transmute(ptr::null::<fn()>()),
-                                     ptr::null_mut());
-            // Get the build log.
-            let mut size = 0 as libc::size_t;
-            let status = clGetProgramBuildInfo(
-                self.prg,
-                device.id,
-                CL_PROGRAM_BUILD_LOG,
-                0,
-                ptr::null_mut(),
-                (&mut size));
-            check(status, "Could not get build log");
-
-            let mut buf : Vec<u8> = repeat(0u8).take(size as usize).collect();
-            let status = clGetProgramBuildInfo(
-                self.prg,
-                device.id,
-                CL_PROGRAM_BUILD_LOG,
-                buf.len() as libc::size_t,
-                buf.as_mut_ptr() as *mut libc::c_void,
-                ptr::null_mut());
-            check(status, "Could not get build log");
-
-            let log = String::from_utf8_lossy(&buf[..]);
-            if ret == CL_SUCCESS as cl_int {
-                Ok(log.into_owned())
-            } else {
-                Err(log.into_owned())
+        let mut logs = try!(self.build_with_options(&[*device], ""));
+        Ok(logs.remove(device).unwrap_or(String::new()))
+    }
+
+    /// Build the program for several devices at once, passing `options` to
+    /// the compiler.
+    ///
+    /// An empty `devices` slice builds for every device associated with the
+    /// program. On success the build log of each device is returned.
+    pub fn build_with_options(&self, devices: &[Device], options: &str)
+        -> Result<HashMap<Device, String>, BuildError>
+    {
+        let fail = |error: Error| {
+            BuildError {
+                error: error,
+                options: options.to_string(),
+                devices: HashMap::new(),
+            }
+        };
+
+        let c_options = match CString::new(options) {
+            Ok(o) => o,
+            Err(_) => return Err(fail(Error::new(CL_INVALID_BUILD_OPTIONS as cl_int,
+                                                 "clBuildProgram",
+                                                 "Build options contain a NUL byte")))
+        };
+        let ids: Vec<cl_device_id> = devices.iter().map(|d| d.id).collect();
+
+        let ret = unsafe {
+            clBuildProgram(self.prg,
+                           ids.len() as cl_uint,
+                           if ids.is_empty() { ptr::null() } else { ids.as_ptr() },
+                           c_options.as_ptr(),
+                           None,
+                           ptr::null_mut())
+        };
+
+        let devices = if devices.is_empty() {
+            self.devices()
+        } else {
+            Ok(devices.to_vec())
+        };
+
+        if ret == CL_SUCCESS as cl_int {
+            let mut logs = HashMap::new();
+            for device in try!(devices.map_err(&fail)).into_iter() {
+                let log = try!(self.build_log(&device).map_err(&fail));
+                logs.insert(device, log);
+            }
+            Ok(logs)
+        } else {
+            // The build error is what matters; the status and log of each
+            // device are reported as far as they can be queried.
+            let mut results = HashMap::new();
+            for device in devices.unwrap_or(Vec::new()).into_iter() {
+                let status = self.build_status(&device).unwrap_or(BuildStatus::Error);
+                let log = self.build_log(&device).unwrap_or(String::new());
+                results.insert(device, (status, log));
             }
+
+            Err(BuildError {
+                error: Error::new(ret, "clBuildProgram", "Failed to build program"),
+                options: options.to_string(),
+                devices: results,
+            })
+        }
+    }
+
+    /// The outcome of the last build of this program for `device`.
+    pub fn build_status(&self, device: &Device) -> Result<BuildStatus, Error>
+    {
+        get_info::<cl_build_status, _>("clGetProgramBuildInfo", CL_PROGRAM_BUILD_STATUS,
+                                       |size, value, size_ret| unsafe {
+            clGetProgramBuildInfo(self.prg, device.id, CL_PROGRAM_BUILD_STATUS, size, value, size_ret)
+        }).map(BuildStatus::from_cl)
+    }
+
+    /// The compiler output of the last build of this program for `device`.
+    pub fn build_log(&self, device: &Device) -> Result<String, Error>
+    {
+        get_info_string("clGetProgramBuildInfo", CL_PROGRAM_BUILD_LOG, |size, value, size_ret| unsafe {
+            clGetProgramBuildInfo(self.prg, device.id, CL_PROGRAM_BUILD_LOG, size, value, size_ret)
+        })
+    }
+
+    /// The devices the program is associated with.
+    pub fn devices(&self) -> Result<Vec<Device>, Error>
+    {
+        let ids = try!(get_info_vec::<cl_device_id, _>("clGetProgramInfo", CL_PROGRAM_DEVICES,
+                                                       |size, value, size_ret| unsafe {
+            clGetProgramInfo(self.prg, CL_PROGRAM_DEVICES, size, value, size_ret)
+        }));
+        Ok(ids.into_iter().map(|id| Device { id: id }).collect())
+    }
+
+    /// The source the program was created from; empty for binaries.
+    pub fn source(&self) -> Result<String, Error>
+    {
+        get_info_string("clGetProgramInfo", CL_PROGRAM_SOURCE, |size, value, size_ret| unsafe {
+            clGetProgramInfo(self.prg, CL_PROGRAM_SOURCE, size, value, size_ret)
+        })
+    }
+
+    /// The number of kernels in the program. It must have been built.
+    pub fn num_kernels(&self) -> Result<usize, Error>
+    {
+        self.create_kernels().map(|kernels| kernels.len())
+    }
+
+    /// The names of the kernels in the program. It must have been built.
+    pub fn kernel_names(&self) -> Result<Vec<String>, Error>
+    {
+        let kernels = try!(self.create_kernels());
+        let mut names = Vec::with_capacity(kernels.len());
+        for kernel in kernels.iter() {
+            names.push(try!(kernel.function_name()));
         }
+        Ok(names)
     }
 
-    pub fn create_kernel(&self, name: &str) -> Kernel {
+    pub fn create_kernel(&self, name: &str) -> Result<Kernel, Error> {
         create_kernel(self, name)
     }
+
+    /// One kernel for every kernel function in the program.
+    pub fn create_kernels(&self) -> Result<Vec<Kernel>, Error>
+    {
+        unsafe {
+            let mut count = 0;
+            let status = clCreateKernelsInProgram(self.prg, 0, ptr::null_mut(), &mut count);
+            try!(check_status(status, "clCreateKernelsInProgram", "Failed to count kernels"));
+
+            let mut kernels = vec![ptr::null_mut(); count as usize];
+            let status = clCreateKernelsInProgram(self.prg, count, kernels.as_mut_ptr(),
+                                                  ptr::null_mut());
+            try!(check_status(status, "clCreateKernelsInProgram", "Failed to create kernels"));
+            Ok(kernels.into_iter().map(|k| Kernel { kernel: k }).collect())
+        }
+    }
 }
 
 pub struct Kernel {
@@ -646,24 +2942,57 @@ impl Drop for Kernel
 }
 
 impl Kernel {
-    pub fn set_arg<T: KernelArg>(&self, i: usize, x: &T)
+    fn info<T: Copy>(&self, name: cl_kernel_info) -> Result<T, Error>
+    {
+        get_info("clGetKernelInfo", name, |size, value, size_ret| unsafe {
+            clGetKernelInfo(self.kernel, name, size, value, size_ret)
+        })
+    }
+
+    pub fn function_name(&self) -> Result<String, Error>
+    {
+        get_info_string("clGetKernelInfo", CL_KERNEL_FUNCTION_NAME, |size, value, size_ret| unsafe {
+            clGetKernelInfo(self.kernel, CL_KERNEL_FUNCTION_NAME, size, value, size_ret)
+        })
+    }
+
+    pub fn num_args(&self) -> Result<u32, Error>
+    {
+        self.info::<cl_uint>(CL_KERNEL_NUM_ARGS)
+    }
+
+    /// The program the kernel was created from.
+    pub fn program(&self) -> Result<Program, Error>
+    {
+        let prg = try!(self.info::<cl_program>(CL_KERNEL_PROGRAM));
+        unsafe {
+            clRetainProgram(prg);
+        }
+        Ok(Program { prg: prg })
+    }
+
+    pub fn set_arg<T: KernelArg>(&self, i: usize, x: &T) -> Result<(), Error>
     {
         set_kernel_arg(self, i as cl::cl_uint, x)
     }
 }
 
-pub fn create_kernel(program: &Program, kernel: & str) -> Kernel
+pub fn create_kernel(program: &Program, kernel: & str) -> Result<Kernel, Error>
 {
     unsafe {
         let mut errcode = 0;
-        let str = CString::new(kernel).unwrap();
+        let str = match CString::new(kernel) {
+            Ok(str) => str,
+            Err(_) => return Err(Error::new(CL_INVALID_VALUE as cl_int, "clCreateKernel",
+                                            "Kernel name contains a NUL byte"))
+        };
         let kernel = clCreateKernel(program.prg,
                                     str.as_ptr(),
                                     (&mut errcode));
 
-        check(errcode, "Failed to create kernel!");
+        try!(check_status(errcode, "clCreateKernel", "Failed to create kernel!"));
 
-        Kernel { kernel: kernel }
+        Ok(Kernel { kernel: kernel })
     }
 }
 
@@ -707,7 +3036,7 @@ impl KernelArg for [f64; 3] {
 
 pub fn set_kernel_arg<T: KernelArg>(kernel: & Kernel,
                                     position: cl_uint,
-                                    arg: &T)
+                                    arg: &T) -> Result<(), Error>
 {
     unsafe
     {
@@ -716,7 +3045,7 @@ pub fn set_kernel_arg<T: KernelArg>(kernel: & Kernel,
                                  size,
                                  p);
 
-        check(ret, "Failed to set kernel arg!");
+        check_status(ret, "clSetKernelArg", "Failed to set kernel arg!")
     }
 }
 
@@ -726,8 +3055,170 @@ pub struct Event
     pub event: cl_event,
 }
 
+/// The execution status of the command behind an event.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum ExecutionStatus {
+    Queued,
+    Submitted,
+    Running,
+    Complete,
+    /// The command terminated abnormally with this error code.
+    Error(cl_int),
+}
+
+impl ExecutionStatus {
+    fn from_cl(status: cl_int) -> ExecutionStatus {
+        match status {
+            s if s == CL_QUEUED as cl_int => ExecutionStatus::Queued,
+            s if s == CL_SUBMITTED as cl_int => ExecutionStatus::Submitted,
+            s if s == CL_RUNNING as cl_int => ExecutionStatus::Running,
+            s if s == CL_COMPLETE as cl_int => ExecutionStatus::Complete,
+            s => ExecutionStatus::Error(s)
+        }
+    }
+}
+
+/// The kind of command behind an event (`cl_command_type`).
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum CommandType {
+    NDRangeKernel,
+    Task,
+    NativeKernel,
+    ReadBuffer,
+    WriteBuffer,
+    CopyBuffer,
+    ReadImage,
+    WriteImage,
+    CopyImage,
+    CopyImageToBuffer,
+    CopyBufferToImage,
+    MapBuffer,
+    MapImage,
+    UnmapMemObject,
+    Marker,
+    AcquireGLObjects,
+    ReleaseGLObjects,
+    ReadBufferRect,
+    WriteBufferRect,
+    CopyBufferRect,
+    User,
+    Barrier,
+    MigrateMemObjects,
+    FillBuffer,
+    FillImage,
+    /// A command type defined by an extension.
+    Other(cl_command_type),
+}
+
+impl CommandType {
+    fn from_cl(t: cl_command_type) -> CommandType {
+        let types = [
+            (CL_COMMAND_NDRANGE_KERNEL, CommandType::NDRangeKernel),
+            (CL_COMMAND_TASK, CommandType::Task),
+            (CL_COMMAND_NATIVE_KERNEL, CommandType::NativeKernel),
+            (CL_COMMAND_READ_BUFFER, CommandType::ReadBuffer),
+            (CL_COMMAND_WRITE_BUFFER, CommandType::WriteBuffer),
+            (CL_COMMAND_COPY_BUFFER, CommandType::CopyBuffer),
+            (CL_COMMAND_READ_IMAGE, CommandType::ReadImage),
+            (CL_COMMAND_WRITE_IMAGE, CommandType::WriteImage),
+            (CL_COMMAND_COPY_IMAGE, CommandType::CopyImage),
+            (CL_COMMAND_COPY_IMAGE_TO_BUFFER, CommandType::CopyImageToBuffer),
+            (CL_COMMAND_COPY_BUFFER_TO_IMAGE, CommandType::CopyBufferToImage),
+            (CL_COMMAND_MAP_BUFFER, CommandType::MapBuffer),
+            (CL_COMMAND_MAP_IMAGE, CommandType::MapImage),
+            (CL_COMMAND_UNMAP_MEM_OBJECT, CommandType::UnmapMemObject),
+            (CL_COMMAND_MARKER, CommandType::Marker),
+            (CL_COMMAND_ACQUIRE_GL_OBJECTS, CommandType::AcquireGLObjects),
+            (CL_COMMAND_RELEASE_GL_OBJECTS, CommandType::ReleaseGLObjects),
+            (CL_COMMAND_READ_BUFFER_RECT, CommandType::ReadBufferRect),
+            (CL_COMMAND_WRITE_BUFFER_RECT, CommandType::WriteBufferRect),
+            (CL_COMMAND_COPY_BUFFER_RECT, CommandType::CopyBufferRect),
+            (CL_COMMAND_USER, CommandType::User),
+            (CL_COMMAND_BARRIER, CommandType::Barrier),
+            (CL_COMMAND_MIGRATE_MEM_OBJECTS, CommandType::MigrateMemObjects),
+            (CL_COMMAND_FILL_BUFFER, CommandType::FillBuffer),
+            (CL_COMMAND_FILL_IMAGE, CommandType::FillImage),
+        ];
+        types.iter().find(|&&(bits, _)| bits == t).map_or(CommandType::Other(t), |&(_, c)| c)
+    }
+}
+
+/// `pfn_notify` for `Event::on_status`; calls the closure in `user_data`
+/// and releases the reference taken when it was registered.
+extern fn event_callback<F>(event: cl_event, status: cl_int, user_data: *mut libc::c_void)
+    where F: FnOnce(Result<(), Error>)
+{
+    unsafe {
+        let f = *Box::from_raw(user_data as *mut F);
+        let result = if status < 0 {
+            Err(Error::new(status, "clSetEventCallback", "The command terminated abnormally"))
+        } else {
+            Ok(())
+        };
+        guard_callback("an event callback", move || f(result));
+        clReleaseEvent(event);
+    }
+}
+
 impl Event {
-    fn get_time(&self, param: cl_uint) -> u64
+    fn info<T: Copy>(&self, name: cl_event_info) -> Result<T, Error>
+    {
+        get_info("clGetEventInfo", name, |size, value, size_ret| unsafe {
+            clGetEventInfo(self.event, name, size, value, size_ret)
+        })
+    }
+
+    pub fn status(&self) -> Result<ExecutionStatus, Error>
+    {
+        self.info::<cl_int>(CL_EVENT_COMMAND_EXECUTION_STATUS).map(ExecutionStatus::from_cl)
+    }
+
+    pub fn command_type(&self) -> Result<CommandType, Error>
+    {
+        self.info::<cl_command_type>(CL_EVENT_COMMAND_TYPE).map(CommandType::from_cl)
+    }
+
+    /// The queue the command was enqueued on; `None` for user events.
+    pub fn queue(&self) -> Result<Option<CommandQueue>, Error>
+    {
+        let queue = try!(self.info::<cl_command_queue>(CL_EVENT_COMMAND_QUEUE));
+        if queue.is_null() {
+            return Ok(None);
+        }
+        let ctx = try!(self.info::<cl_context>(CL_EVENT_CONTEXT));
+        unsafe {
+            try!(check_status(clRetainCommandQueue(queue), "clRetainCommandQueue",
+                              "Could not retain command queue"));
+        }
+        Ok(Some(CommandQueue { cqueue: queue, state: ContextState::of(ctx) }))
+    }
+
+    /// Waits up to `timeout` for the command to complete by polling its
+    /// status, after flushing its queue. Returns whether it completed; a
+    /// command that terminated abnormally is an error.
+    pub fn wait_timeout(&self, timeout: Duration) -> Result<bool, Error>
+    {
+        if let Some(queue) = try!(self.queue()) {
+            try!(queue.flush());
+        }
+        let start = Instant::now();
+        loop {
+            match try!(self.status()) {
+                ExecutionStatus::Complete => return Ok(true),
+                ExecutionStatus::Error(code) =>
+                    return Err(Error::new(code, "clGetEventInfo",
+                                          "The command terminated abnormally")),
+                _ => ()
+            }
+            let elapsed = start.elapsed();
+            if elapsed >= timeout {
+                return Ok(false);
+            }
+            thread::sleep(cmp::min(timeout - elapsed, Duration::from_millis(1)));
+        }
+    }
+
+    fn get_time(&self, param: cl_uint) -> Result<u64, Error>
     {
         unsafe {
             let mut time: cl_ulong = 0;
@@ -737,30 +3228,96 @@ impl Event {
                                     (&mut time as *mut u64) as *mut libc::c_void,
                                     ptr::null_mut());
 
-            check(ret, "Failed to get profiling info");
-            time as u64
+            if ret == CL_PROFILING_INFO_NOT_AVAILABLE as cl_int {
+                return Err(Error::new(ret, "clGetEventProfilingInfo", self.no_profiling_reason()));
+            }
+            try!(check_status(ret, "clGetEventProfilingInfo", "Failed to get profiling info"));
+            Ok(time as u64)
+        }
+    }
+
+    fn no_profiling_reason(&self) -> &'static str
+    {
+        let queue = get_info::<cl_command_queue, _>(
+            "clGetEventInfo", CL_EVENT_COMMAND_QUEUE, |size, value, size_ret| unsafe {
+                clGetEventInfo(self.event, CL_EVENT_COMMAND_QUEUE, size, value, size_ret)
+            });
+        let properties = queue.and_then(|queue| {
+            get_info::<cl_command_queue_properties, _>(
+                "clGetCommandQueueInfo", CL_QUEUE_PROPERTIES, |size, value, size_ret| unsafe {
+                    clGetCommandQueueInfo(queue, CL_QUEUE_PROPERTIES, size, value, size_ret)
+                })
+        });
+        match properties {
+            Ok(bits) if bits & CL_QUEUE_PROFILING_ENABLE == 0 =>
+                "Profiling info is not available: the command queue was created without \
+                 profiling",
+            _ => "Profiling info is not available: the command has not completed, or it is \
+                  not a profiled command"
         }
     }
 
-    pub fn queue_time(&self) -> u64
+    pub fn queue_time(&self) -> Result<u64, Error>
     {
         self.get_time(CL_PROFILING_COMMAND_QUEUED)
     }
 
-    pub fn submit_time(&self) -> u64
+    pub fn submit_time(&self) -> Result<u64, Error>
     {
         self.get_time(CL_PROFILING_COMMAND_SUBMIT)
     }
 
-    pub fn start_time(&self) -> u64
+    pub fn start_time(&self) -> Result<u64, Error>
     {
         self.get_time(CL_PROFILING_COMMAND_START)
     }
 
-    pub fn end_time(&self) -> u64
+    pub fn end_time(&self) -> Result<u64, Error>
     {
         self.get_time(CL_PROFILING_COMMAND_END)
     }
+
+    /// Calls `f` once the command has completed, or with an error if it
+    /// terminated abnormally.
+    pub fn on_complete<F>(&self, f: F) -> Result<(), Error>
+        where F: FnOnce(Result<(), Error>) + Send + 'static
+    {
+        self.on_status(ExecutionStatus::Complete, f)
+    }
+
+    /// Calls `f` once the command has reached `status`, which must be
+    /// `Submitted`, `Running` or `Complete`; OpenCL 1.1 only supports
+    /// `Complete`. `f` may run on a driver thread and must not block on
+    /// OpenCL commands; a panic in it is logged and otherwise ignored. The
+    /// event is kept alive until then.
+    pub fn on_status<F>(&self, status: ExecutionStatus, f: F) -> Result<(), Error>
+        where F: FnOnce(Result<(), Error>) + Send + 'static
+    {
+        let api = "clSetEventCallback";
+        let callback_type = match status {
+            ExecutionStatus::Submitted => CL_SUBMITTED,
+            ExecutionStatus::Running => CL_RUNNING,
+            ExecutionStatus::Complete => CL_COMPLETE,
+            _ => return Err(Error::new(CL_INVALID_VALUE as cl_int, api,
+                                       &format!("Cannot register a callback for {:?}", status)))
+        };
+
+        unsafe {
+            try!(check_status(clRetainEvent(self.event), "clRetainEvent",
+                              "Could not retain event"));
+            let user_data = Box::into_raw(Box::new(f));
+            let ret = clSetEventCallback(self.event,
+                                         callback_type as cl_int,
+                                         event_callback::<F>,
+                                         user_data as *mut libc::c_void);
+            if let Err(e) = check_status(ret, api, "Could not set event callback") {
+                drop(Box::from_raw(user_data));
+                clReleaseEvent(self.event);
+                return Err(e);
+            }
+        }
+        Ok(())
+    }
 }
 
 impl Drop for Event
@@ -772,14 +3329,79 @@ impl Drop for Event
     }
 }
 
+/// An event set by the host, created by `Context::create_user_event`. It can
+/// be completed or failed once, from any thread; dropping it unset fails it
+/// with `CL_INVALID_EVENT` so that commands waiting on it are not blocked
+/// forever.
+/// Only `&UserEvent` is an `EventList`, so that passing it to an enqueue
+/// does not drop it.
+pub struct UserEvent
+{
+    event: Event,
+    set: AtomicBool,
+}
+
+unsafe impl Sync for UserEvent {}
+unsafe impl Send for UserEvent {}
+
+impl UserEvent {
+    fn set_status(&self, status: cl_int) -> Result<(), Error>
+    {
+        let api = "clSetUserEventStatus";
+        if self.set.swap(true, Ordering::SeqCst) {
+            return Err(Error::new(CL_INVALID_OPERATION as cl_int, api,
+                                  "The user event has already been set"));
+        }
+        let ret = unsafe { clSetUserEventStatus(self.event.event, status) };
+        let result = check_status(ret, api, "Could not set user event status");
+        if result.is_err() {
+            self.set.store(false, Ordering::SeqCst);
+        }
+        result
+    }
+
+    /// Marks the event complete, releasing the commands waiting on it.
+    pub fn complete(&self) -> Result<(), Error>
+    {
+        self.set_status(CL_COMPLETE as cl_int)
+    }
+
+    /// Terminates the event with the error `code`, which must be negative.
+    /// Commands waiting on it fail.
+    pub fn fail(&self, code: cl_int) -> Result<(), Error>
+    {
+        if code >= 0 {
+            return Err(Error::new(CL_INVALID_VALUE as cl_int, "clSetUserEventStatus",
+                                  &format!("Error code {} is not negative", code)));
+        }
+        self.set_status(code)
+    }
+
+    pub fn event(&self) -> &Event
+    {
+        &self.event
+    }
+}
+
+impl Drop for UserEvent
+{
+    fn drop(&mut self) {
+        if !self.set.load(Ordering::SeqCst) {
+            if let Err(e) = self.set_status(CL_INVALID_EVENT as cl_int) {
+                error!("{}", e);
+            }
+        }
+    }
+}
+
 pub trait EventList {
     fn as_event_list<T, F: FnOnce(*const cl_event, cl_uint) -> T>(&self, F) -> T;
 
-    fn wait(&self) {
+    fn wait(&self) -> Result<(), Error> {
         self.as_event_list(|p, len| {
             unsafe {
                 let status = clWaitForEvents(len, p);
-                check(status, "Error waiting for event(s)");
+                check_status(status, "clWaitForEvents", "Error waiting for event(s)")
             }
         })
     }
@@ -801,6 +3423,14 @@ impl EventList for Event {
     }
 }
 
+impl<'r> EventList for &'r UserEvent {
+    fn as_event_list<T, F>(&self, f: F) -> T
+        where F: FnOnce(*const cl_event, cl_uint) -> T
+    {
+        f(&self.event.event, 1 as cl_uint)
+    }
+}
+
 impl<T: EventList> EventList for Option<T> {
     fn as_event_list<T2, F>(&self, f: F) -> T2
         where F: FnOnce(*const cl_event, cl_uint) -> T2
@@ -835,10 +3465,145 @@ impl EventList for () {
 }
 
 
+/// The index space of a kernel launch: one to three dimensions, each with
+/// an offset, a global size and optionally a work-group size.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct NDRange {
+    pub offset: Vec<usize>,
+    pub global: Vec<usize>,
+    /// `None` lets the implementation pick the work-group size.
+    pub local: Option<Vec<usize>>,
+}
+
+impl NDRange {
+    /// A range of the given global size, starting at zero.
+    pub fn new(global: &[usize]) -> NDRange
+    {
+        NDRange {
+            offset: repeat(0).take(global.len()).collect(),
+            global: global.to_vec(),
+            local: None,
+        }
+    }
+
+    pub fn offset(mut self, offset: &[usize]) -> NDRange
+    {
+        self.offset = offset.to_vec();
+        self
+    }
+
+    pub fn local(mut self, local: &[usize]) -> NDRange
+    {
+        self.local = Some(local.to_vec());
+        self
+    }
+
+    pub fn dimensions(&self) -> usize
+    {
+        self.global.len()
+    }
+
+    /// Checks that `device` can run a kernel over this range.
+    pub fn validate(&self, device: &Device) -> Result<(), Error>
+    {
+        let api = "clEnqueueNDRangeKernel";
+        let dims = self.dimensions();
+        let max_dims = try!(device.max_work_item_dimensions()) as usize;
+        if dims < 1 || dims > max_dims {
+            return Err(Error::new(CL_INVALID_WORK_DIMENSION as cl_int, api,
+                                  &format!("NDRange has {} dimensions, the device supports \
+                                            1 to {}", dims, max_dims)));
+        }
+        if self.offset.len() != dims {
+            return Err(Error::new(CL_INVALID_WORK_DIMENSION as cl_int, api,
+                                  &format!("NDRange offset has {} dimensions, the global size \
+                                            has {}", self.offset.len(), dims)));
+        }
+        for (i, &g) in self.global.iter().enumerate() {
+            if g == 0 {
+                return Err(Error::new(CL_INVALID_GLOBAL_WORK_SIZE as cl_int, api,
+                                      &format!("NDRange global size is 0 in dimension {}", i)));
+            }
+            if self.offset[i].checked_add(g).is_none() {
+                return Err(Error::new(CL_INVALID_GLOBAL_WORK_SIZE as cl_int, api,
+                                      &format!("NDRange offset plus global size overflows in \
+                                                dimension {}", i)));
+            }
+        }
+
+        let local = match self.local {
+            Some(ref local) => local,
+            None => return Ok(())
+        };
+        if local.len() != dims {
+            return Err(Error::new(CL_INVALID_WORK_DIMENSION as cl_int, api,
+                                  &format!("NDRange local size has {} dimensions, the global \
+                                            size has {}", local.len(), dims)));
+        }
+        let max_sizes = try!(device.max_work_item_sizes());
+        for (i, (&l, &g)) in local.iter().zip(self.global.iter()).enumerate() {
+            if l == 0 || l > max_sizes[i] {
+                return Err(Error::new(CL_INVALID_WORK_ITEM_SIZE as cl_int, api,
+                                      &format!("NDRange local size {} in dimension {} is not \
+                                                between 1 and the device's maximum of {}",
+                                               l, i, max_sizes[i])));
+            }
+            if g % l != 0 {
+                return Err(Error::new(CL_INVALID_WORK_GROUP_SIZE as cl_int, api,
+                                      &format!("NDRange global size {} in dimension {} is not \
+                                                a multiple of the local size {}", g, i, l)));
+            }
+        }
+        let group = local.iter().fold(1, |n, &l| n * l);
+        let max_group = try!(device.max_work_group_size());
+        if group > max_group {
+            return Err(Error::new(CL_INVALID_WORK_GROUP_SIZE as cl_int, api,
+                                  &format!("NDRange work-group size {} exceeds the device's \
+                                            maximum of {}", group, max_group)));
+        }
+        Ok(())
+    }
+}
+
+impl From<usize> for NDRange {
+    fn from(global: usize) -> NDRange { NDRange::new(&[global]) }
+}
+
+impl From<[usize; 1]> for NDRange {
+    fn from(global: [usize; 1]) -> NDRange { NDRange::new(&global) }
+}
+
+impl From<[usize; 2]> for NDRange {
+    fn from(global: [usize; 2]) -> NDRange { NDRange::new(&global) }
+}
+
+impl From<[usize; 3]> for NDRange {
+    fn from(global: [usize; 3]) -> NDRange { NDRange::new(&global) }
+}
+
+impl From<(usize, usize)> for NDRange {
+    fn from(global: (usize, usize)) -> NDRange { NDRange::new(&[global.0, global.1]) }
+}
+
+impl From<(usize, usize, usize)> for NDRange {
+    fn from(global: (usize, usize, usize)) -> NDRange {
+        NDRange::new(&[global.0, global.1, global.2])
+    }
+}
+
 pub trait KernelIndex
 {
     fn num_dimensions(dummy_self: Option<Self>) -> cl_uint where Self: Sized;
     fn get_ptr(&self) -> *const libc::size_t;
+
+    /// The size in each dimension.
+    fn sizes(&self) -> Vec<usize> where Self: Sized
+    {
+        let dims = KernelIndex::num_dimensions(None::<Self>) as usize;
+        unsafe {
+            slice::from_raw_parts(self.get_ptr(), dims).iter().map(|&n| n as usize).collect()
+        }
+    }
 }
 
 impl KernelIndex for isize
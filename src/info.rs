@@ -0,0 +1,53 @@
+//! Helpers for the `clGet*Info` family of functions.
+
+use libc;
+use std::mem;
+use std::ptr;
+
+use cl::*;
+use error::{Error, check_status};
+
+/// Reads a fixed-size value through one of the `clGet*Info` functions.
+pub fn get_info<T: Copy, F>(api: &'static str, name: cl_uint, f: F) -> Result<T, Error>
+    where F: Fn(libc::size_t, *mut libc::c_void, *mut libc::size_t) -> cl_int
+{
+    unsafe {
+        let mut value: T = mem::zeroed();
+        let status = f(mem::size_of::<T>() as libc::size_t,
+                       (&mut value as *mut T) as *mut libc::c_void,
+                       ptr::null_mut());
+        try!(check_status(status, api, &format!("Could not get info 0x{:x}", name)));
+        Ok(value)
+    }
+}
+
+/// Reads an array through one of the `clGet*Info` functions.
+pub fn get_info_vec<T: Copy, F>(api: &'static str, name: cl_uint, f: F) -> Result<Vec<T>, Error>
+    where F: Fn(libc::size_t, *mut libc::c_void, *mut libc::size_t) -> cl_int
+{
+    unsafe {
+        let mut size = 0 as libc::size_t;
+        let status = f(0, ptr::null_mut(), &mut size);
+        try!(check_status(status, api, &format!("Could not determine size of info 0x{:x}", name)));
+
+        let len = size as usize / mem::size_of::<T>();
+        let mut buf: Vec<T> = vec![mem::zeroed(); len];
+        let status = f((len * mem::size_of::<T>()) as libc::size_t,
+                       buf.as_mut_ptr() as *mut libc::c_void,
+                       &mut size);
+        try!(check_status(status, api, &format!("Could not get info 0x{:x}", name)));
+        buf.truncate(size as usize / mem::size_of::<T>());
+        Ok(buf)
+    }
+}
+
+/// Reads a NUL-terminated string through one of the `clGet*Info` functions.
+pub fn get_info_string<F>(api: &'static str, name: cl_uint, f: F) -> Result<String, Error>
+    where F: Fn(libc::size_t, *mut libc::c_void, *mut libc::size_t) -> cl_int
+{
+    let mut buf = try!(get_info_vec::<u8, F>(api, name, f));
+    while buf.last() == Some(&0) {
+        buf.pop();
+    }
+    Ok(String::from_utf8_lossy(&buf[..]).into_owned())
+}
@@ -28,3 +28,6 @@ pub mod hl;
 pub mod util;
 pub mod mem;
 pub mod array;
+mod info;
+
+pub use error::Error;
@@ -3,14 +3,22 @@
 use libc::{size_t, c_void};
 use std::marker::{PhantomData};
 use std::mem;
-use std::ptr;
 use std::vec::Vec;
 
 use cl::*;
 use cl::ll::*;
+use cl::CLStatus::CL_INVALID_VALUE;
 
 use hl::KernelArg;
-use error::check;
+use error::Error;
+use info::get_info;
+
+fn mem_info<T: Copy>(buf: cl_mem, name: cl_mem_info) -> Result<T, Error>
+{
+    get_info("clGetMemObjectInfo", name, |size, value, size_ret| unsafe {
+        clGetMemObjectInfo(buf, name, size, value, size_ret)
+    })
+}
 
 pub trait Buffer<T> {
     unsafe fn id_ptr(&self) -> *const cl_mem;
@@ -21,22 +29,46 @@ pub trait Buffer<T> {
         }
     }
 
+    /// The flags the buffer was created with.
+    fn flags(&self) -> Result<cl_mem_flags, Error>
+    {
+        mem_info(self.id(), CL_MEM_FLAGS)
+    }
+
+    /// In bytes.
+    fn size(&self) -> Result<usize, Error>
+    {
+        mem_info(self.id(), CL_MEM_SIZE)
+    }
+
+    /// How many times the buffer is currently mapped.
+    fn map_count(&self) -> Result<u32, Error>
+    {
+        mem_info(self.id(), CL_MEM_MAP_COUNT)
+    }
+
+    /// The host memory backing the buffer if it was created with
+    /// `CL_MEM_USE_HOST_PTR`, null otherwise.
+    fn host_ptr(&self) -> Result<*mut c_void, Error>
+    {
+        mem_info(self.id(), CL_MEM_HOST_PTR)
+    }
+
+    #[deprecated(since = "0.3.0", note = "use `size()`, which returns errors instead of panicking")]
     fn byte_len(&self) -> size_t
     {
-        unsafe {
-            let mut size : size_t = 0;
-            let err = clGetMemObjectInfo(self.id(),
-                                         CL_MEM_SIZE,
-                                         mem::size_of::<size_t>() as size_t,
-                                         (&mut size as *mut size_t) as *mut c_void,
-                                         ptr::null_mut());
-
-            check(err, "Failed to read memory size");
-            size
-        }
+        self.size().expect("Failed to read memory size") as size_t
     }
 
-    fn len(&self) -> usize { self.byte_len() as usize / mem::size_of::<T>() }
+    /// In elements. Fails for zero-sized `T`.
+    fn len(&self) -> Result<usize, Error>
+    {
+        if mem::size_of::<T>() == 0 {
+            return Err(Error::new(CL_INVALID_VALUE as cl_int, "clGetMemObjectInfo",
+                                  "Buffers of zero-sized elements are not supported"));
+        }
+        Ok(try!(self.size()) / mem::size_of::<T>())
+    }
 }
 
 pub struct CLBuffer<T> {
@@ -82,8 +114,11 @@ pub trait Put<T, B> {
         where F: FnOnce(*const c_void, size_t) -> cl_mem;
 }
 
-pub trait Get<B, T> {
-    fn get<F: FnOnce(size_t, *mut c_void, size_t)>(mem: &B, F) -> Self;
+pub trait Get<B, T>: Sized {
+    /// `F` reads the given bytes of the buffer into host memory and reports
+    /// whether that succeeded.
+    fn get<F>(mem: &B, F) -> Result<Self, Error>
+        where F: FnOnce(size_t, *mut c_void, size_t) -> Result<(), Error>;
 }
 
 pub trait Write {
@@ -133,15 +168,17 @@ impl<T> Put<T, CLBuffer<T>> for Vec<T>
 
 impl<T> Get<CLBuffer<T>, T> for Vec<T>
 {
-    fn get<F>(mem: &CLBuffer<T>, f: F) -> Vec<T>
-        where F: FnOnce(size_t, *mut c_void, size_t)
+    fn get<F>(mem: &CLBuffer<T>, f: F) -> Result<Vec<T>, Error>
+        where F: FnOnce(size_t, *mut c_void, size_t) -> Result<(), Error>
     {
-        let mut v: Vec<T> = Vec::with_capacity(mem.len());
+        let len = try!(mem.len());
+        let mut v: Vec<T> = Vec::with_capacity(len);
+        try!(f(0, v.as_mut_ptr() as *mut c_void, (len * mem::size_of::<T>()) as size_t));
+        // The elements have been read in full.
         unsafe {
-            v.set_len(mem.len());
+            v.set_len(len);
         }
-        f(0, v.as_ptr() as *mut c_void, (v.len() * mem::size_of::<T>()) as size_t);
-        v
+        Ok(v)
     }
 }
 
@@ -165,15 +202,33 @@ impl<'r, T> Read for &'r mut [T]
     }
 }
 
+impl<T> Write for Vec<T>
+{
+    fn write<F>(&self, f: F)
+        where F: FnOnce(size_t, *const c_void, size_t)
+    {
+        f(0, self.as_ptr() as *const c_void, (self.len() * mem::size_of::<T>()) as size_t)
+    }
+}
+
+impl<T> Read for Vec<T>
+{
+    fn read<F>(&mut self, f: F)
+        where F: FnOnce(size_t, *mut c_void, size_t)
+    {
+        f(0, self.as_mut_ptr() as *mut c_void, (self.len() * mem::size_of::<T>()) as size_t)
+    }
+}
+
 macro_rules! get_arg (
     ($t:ty) => (impl Get<CLBuffer<$t>, $t> for $t
         {
-            fn get<F>(_: &CLBuffer<$t>, f: F) -> $t
-                where F: FnOnce(size_t, *mut c_void, size_t)
+            fn get<F>(_: &CLBuffer<$t>, f: F) -> Result<$t, Error>
+                where F: FnOnce(size_t, *mut c_void, size_t) -> Result<(), Error>
             {
                 let mut v: $t = 0 as $t;
-                f(0, (&mut v as *mut $t) as *mut c_void, mem::size_of::<$t>() as size_t);
-                v as $t
+                try!(f(0, (&mut v as *mut $t) as *mut c_void, mem::size_of::<$t>() as size_t));
+                Ok(v as $t)
             }
         })
 );
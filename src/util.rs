@@ -1,22 +1,51 @@
 //! Utility functions
+//!
+//! The device picked by `create_compute_context`,
+//! `create_compute_context_prefer` and `DeviceSelector` can be pinned
+//! without recompiling through these environment variables:
+//!
+//! * `RUST_OPENCL_PLATFORM`: index of the platform, or part of its name
+//! * `RUST_OPENCL_DEVICE`: index of the device among those left by the other
+//!   variables, or part of its name
+//! * `RUST_OPENCL_DEVICE_TYPE`: `cpu`, `gpu`, `accelerator`, `default`,
+//!   `custom` or `all`
+//!
+//! Names are matched ignoring case.
 
+use std::cmp::Ordering;
+use std::env;
+
+use cl::cl_int;
+use cl::CLStatus::{CL_DEVICE_NOT_FOUND, CL_PLATFORM_NOT_FOUND_KHR, CL_INVALID_DEVICE_TYPE};
+use error::Error;
 use hl::*;
 
-pub fn create_compute_context() -> Result<(Device, Context, CommandQueue), &'static str>
+fn create_on(device: Device) -> Result<(Device, Context, CommandQueue), Error>
+{
+    let context = try!(device.create_context());
+    let queue = try!(context.create_command_queue(&device));
+    Ok((device, context, queue))
+}
+
+pub fn create_compute_context() -> Result<(Device, Context, CommandQueue), Error>
 {
-    let platforms = get_platforms();
+    let overrides = DeviceOverride::from_env();
+    if !overrides.is_empty() {
+        let mut devices = try!(overrides.devices());
+        return create_on(devices.remove(0));
+    }
+
+    let platforms = try!(get_platforms());
     if platforms.len() == 0 {
-        return Err("No platform found");
+        return Err(Error::new(CL_PLATFORM_NOT_FOUND_KHR as cl_int, "clGetPlatformIDs",
+                              "No platform found"));
     }
 
-    let mut devices = platforms[0].get_devices();
+    let mut devices = try!(platforms[0].get_devices());
     if devices.len() == 0 {
-        Err("No device found")
+        Err(Error::new(CL_DEVICE_NOT_FOUND as cl_int, "clGetDeviceIDs", "No device found"))
     } else {
-        let device = devices.remove(0);
-        let context = device.create_context();
-        let queue = context.create_command_queue(&device);
-        Ok((device, context, queue))
+        create_on(devices.remove(0))
     }
 }
 
@@ -31,30 +60,433 @@ pub enum PreferedType {
     GPUOnly,
 }
 
-pub fn create_compute_context_prefer(cltype: PreferedType) -> Result<(Device, Context, CommandQueue), &'static str>
+/// Create a context on the first device of the preferred type.
+///
+/// See `DeviceSelector` for finer control over which device is used.
+pub fn create_compute_context_prefer(cltype: PreferedType) -> Result<(Device, Context, CommandQueue), Error>
 {
-    let platforms = get_platforms();
-    for platform in platforms.iter() {
-        let types = match cltype {
-            PreferedType::Any => vec![DeviceType::CPU, DeviceType::GPU],
-            PreferedType::CPUPrefered | PreferedType::CPUOnly => vec![DeviceType::CPU],
-            PreferedType::GPUPrefered | PreferedType::GPUOnly => vec![DeviceType::GPU]
-        };
+    let types = match cltype {
+        PreferedType::Any => vec![DeviceType::CPU, DeviceType::GPU],
+        PreferedType::CPUPrefered | PreferedType::CPUOnly => vec![DeviceType::CPU],
+        PreferedType::GPUPrefered | PreferedType::GPUOnly => vec![DeviceType::GPU]
+    };
+
+    let overrides = DeviceOverride::from_env();
+    let pinned = if overrides.is_empty() { None } else { Some(try!(overrides.devices())) };
 
-        let mut devices = platform.get_devices_by_types(&types[..]);
-        if devices.len() > 0 {
-            let device = devices.remove(0);
-            let context = device.create_context();
-            let queue = context.create_command_queue(&device);
-            return Ok((device, context, queue))
+    let platforms = try!(get_platforms());
+    for platform in platforms.iter() {
+        let devices = try!(platform.get_devices_by_types(&types[..]));
+        for &device in devices.iter() {
+            match pinned {
+                Some(ref pinned) if !pinned.contains(&device) => (),
+                _ => return create_on(device)
+            }
         }
     }
 
+    if let Some(mut pinned) = pinned {
+        return match cltype {
+            PreferedType::CPUOnly |
+            PreferedType::GPUOnly => Err(Error::new(CL_DEVICE_NOT_FOUND as cl_int, "clGetDeviceIDs",
+                                                    "The device selected through RUST_OPENCL_* \
+                                                     is not of the required type")),
+            _ => create_on(pinned.remove(0))
+        };
+    }
+
 
     match cltype {
         PreferedType::Any |
         PreferedType::CPUPrefered |
         PreferedType::GPUPrefered => create_compute_context(),
-        _ => Err("Could not find valid implementation")
+        _ => Err(Error::new(CL_DEVICE_NOT_FOUND as cl_int, "clGetDeviceIDs",
+                            "Could not find valid implementation"))
+    }
+}
+
+/// Picks a device across all platforms from a set of requirements.
+///
+/// Devices that meet every requirement are ranked by the order in which
+/// their type was requested, then by compute units, then by global memory.
+///
+/// ```ignore
+/// let (device, ctx, queue) = DeviceSelector::new()
+///     .device_type(DeviceType::GPU)
+///     .device_type(DeviceType::CPU)
+///     .min_global_mem(1 << 30)
+///     .extension("cl_khr_fp64")
+///     .create_context()
+///     .unwrap();
+/// ```
+#[derive(Clone)]
+pub struct DeviceSelector {
+    types: Vec<DeviceType>,
+    vendor: Option<String>,
+    name: Option<String>,
+    min_global_mem: u64,
+    min_compute_units: u32,
+    extensions: Vec<String>,
+    min_version: Option<ClVersion>,
+    overrides: DeviceOverride,
+}
+
+/// The outcome of `DeviceSelector::select`.
+pub struct Selection {
+    /// Devices meeting every requirement, best first.
+    pub devices: Vec<Device>,
+    /// Every other device, with the reason it was rejected.
+    pub rejected: Vec<(Device, String)>,
+}
+
+impl DeviceSelector {
+    pub fn new() -> DeviceSelector {
+        DeviceSelector {
+            types: Vec::new(),
+            vendor: None,
+            name: None,
+            min_global_mem: 0,
+            min_compute_units: 0,
+            extensions: Vec::new(),
+            min_version: None,
+            overrides: DeviceOverride::from_env(),
+        }
+    }
+
+    /// Replace the override read from the `RUST_OPENCL_*` environment
+    /// variables. Passing `DeviceOverride::new()` ignores the environment.
+    pub fn device_override(mut self, overrides: DeviceOverride) -> DeviceSelector {
+        self.overrides = overrides;
+        self
+    }
+
+    /// Accept devices of type `t`. Types requested first are preferred;
+    /// if no type is requested, every type is accepted.
+    pub fn device_type(mut self, t: DeviceType) -> DeviceSelector {
+        self.types.push(t);
+        self
+    }
+
+    /// Only accept devices whose vendor contains `vendor`, ignoring case.
+    pub fn vendor(mut self, vendor: &str) -> DeviceSelector {
+        self.vendor = Some(vendor.to_lowercase());
+        self
+    }
+
+    /// Only accept devices whose name contains `name`, ignoring case.
+    pub fn name(mut self, name: &str) -> DeviceSelector {
+        self.name = Some(name.to_lowercase());
+        self
+    }
+
+    /// Only accept devices with at least `bytes` of global memory.
+    pub fn min_global_mem(mut self, bytes: u64) -> DeviceSelector {
+        self.min_global_mem = bytes;
+        self
+    }
+
+    pub fn min_compute_units(mut self, units: u32) -> DeviceSelector {
+        self.min_compute_units = units;
+        self
+    }
+
+    /// Only accept devices supporting the extension `ext`, e.g. `cl_khr_fp64`.
+    pub fn extension(mut self, ext: &str) -> DeviceSelector {
+        self.extensions.push(ext.to_string());
+        self
+    }
+
+    /// Only accept devices supporting at least OpenCL `version`.
+    pub fn min_version(mut self, version: ClVersion) -> DeviceSelector {
+        self.min_version = Some(version);
+        self
+    }
+
+    /// Rank every device of every platform against the requirements.
+    pub fn select(&self) -> Result<Selection, Error> {
+        let mut ranked = Vec::new();
+        let mut rejected = Vec::new();
+        let pinned = if self.overrides.is_empty() {
+            None
+        } else {
+            Some(try!(self.overrides.devices()))
+        };
+
+        for platform in try!(get_platforms()).iter() {
+            // Type preference of each device; devices not listed here are of
+            // a type that was not requested.
+            let mut preference: Vec<(Device, usize)> = Vec::new();
+            if self.types.is_empty() {
+                for device in try!(platform.get_devices()).into_iter() {
+                    preference.push((device, 0));
+                }
+            } else {
+                for (rank, &t) in self.types.iter().enumerate() {
+                    for device in try!(platform.get_devices_by_types(&[t])).into_iter() {
+                        if !preference.iter().any(|&(d, _)| d == device) {
+                            preference.push((device, rank));
+                        }
+                    }
+                }
+            }
+
+            for device in try!(platform.get_devices()).into_iter() {
+                if let Some(ref pinned) = pinned {
+                    if !pinned.contains(&device) {
+                        rejected.push((device, "excluded by RUST_OPENCL_* override".to_string()));
+                        continue;
+                    }
+                }
+                let rank = match preference.iter().find(|&&(d, _)| d == device) {
+                    Some(&(_, rank)) => rank,
+                    None => {
+                        rejected.push((device, "device type was not requested".to_string()));
+                        continue;
+                    }
+                };
+                match self.check(&device) {
+                    Ok((units, mem)) => ranked.push((device, rank, units, mem)),
+                    Err(reason) => rejected.push((device, reason))
+                }
+            }
+        }
+
+        ranked.sort_by(|&(_, rank_a, units_a, mem_a), &(_, rank_b, units_b, mem_b)| {
+            match rank_a.cmp(&rank_b) {
+                Ordering::Equal => (),
+                o => return o
+            }
+            match units_b.cmp(&units_a) {
+                Ordering::Equal => (),
+                o => return o
+            }
+            mem_b.cmp(&mem_a)
+        });
+
+        Ok(Selection {
+            devices: ranked.into_iter().map(|(device, _, _, _)| device).collect(),
+            rejected: rejected,
+        })
+    }
+
+    /// Create a context and command queue on the best matching device.
+    ///
+    /// If no device matches, the error lists why each device was rejected.
+    pub fn create_context(&self) -> Result<(Device, Context, CommandQueue), Error> {
+        let selection = try!(self.select());
+        match selection.devices.first() {
+            Some(device) => {
+                let context = try!(device.create_context());
+                let queue = try!(context.create_command_queue(device));
+                Ok((*device, context, queue))
+            }
+            None => {
+                let mut message = "No device matches the selection".to_string();
+                for &(ref device, ref reason) in selection.rejected.iter() {
+                    let name = device.name().unwrap_or(format!("{:?}", device));
+                    message.push_str(&format!("\n  {}: {}", name, reason));
+                }
+                Err(Error::new(CL_DEVICE_NOT_FOUND as cl_int, "clGetDeviceIDs", &message))
+            }
+        }
+    }
+
+    /// Checks every requirement except the device type, returning the
+    /// device's compute units and global memory for ranking, or the reason
+    /// it was rejected.
+    fn check(&self, device: &Device) -> Result<(u32, u64), String> {
+        macro_rules! query (
+            ($e:expr, $what:expr) => (match $e {
+                Ok(v) => v,
+                Err(e) => return Err(format!("could not query {}: {}", $what, e))
+            })
+        );
+
+        if let Some(ref vendor) = self.vendor {
+            let v = query!(device.vendor(), "vendor");
+            if !v.to_lowercase().contains(&vendor[..]) {
+                return Err(format!("vendor \"{}\" does not match \"{}\"", v, vendor));
+            }
+        }
+        if let Some(ref name) = self.name {
+            let n = query!(device.name(), "name");
+            if !n.to_lowercase().contains(&name[..]) {
+                return Err(format!("name does not match \"{}\"", name));
+            }
+        }
+
+        let units = query!(device.max_compute_units(), "compute units");
+        if units < self.min_compute_units {
+            return Err(format!("has {} compute units, {} required", units, self.min_compute_units));
+        }
+        let mem = query!(device.global_mem_size(), "global memory size");
+        if mem < self.min_global_mem {
+            return Err(format!("has {} bytes of global memory, {} required",
+                               mem, self.min_global_mem));
+        }
+
+        if !self.extensions.is_empty() {
+            let available = query!(device.extensions_set(), "extensions");
+            for ext in self.extensions.iter() {
+                if !available.contains(ext) {
+                    return Err(format!("does not support {}", ext));
+                }
+            }
+        }
+
+        if let Some(required) = self.min_version {
+            let version = query!(device.cl_version(), "version");
+            if version < required {
+                return Err(format!("supports OpenCL {}, {} required", version, required));
+            }
+        }
+
+        Ok((units, mem))
+    }
+}
+
+/// Narrows the devices considered for selection down to the ones named by
+/// the user, usually through the `RUST_OPENCL_*` environment variables.
+#[derive(Clone, Debug, Default)]
+pub struct DeviceOverride {
+    platform: Option<String>,
+    device: Option<String>,
+    device_type: Option<String>,
+}
+
+impl DeviceOverride {
+    /// An override that does not restrict anything.
+    pub fn new() -> DeviceOverride {
+        DeviceOverride::default()
+    }
+
+    /// Read `RUST_OPENCL_PLATFORM`, `RUST_OPENCL_DEVICE` and
+    /// `RUST_OPENCL_DEVICE_TYPE`. Unset or empty variables are ignored.
+    pub fn from_env() -> DeviceOverride {
+        fn var(name: &str) -> Option<String> {
+            env::var(name).ok().and_then(|v| {
+                let v = v.trim().to_string();
+                if v.is_empty() { None } else { Some(v) }
+            })
+        }
+
+        DeviceOverride {
+            platform: var("RUST_OPENCL_PLATFORM"),
+            device: var("RUST_OPENCL_DEVICE"),
+            device_type: var("RUST_OPENCL_DEVICE_TYPE"),
+        }
+    }
+
+    /// Only consider the platform with this index, or whose name contains
+    /// this string.
+    pub fn platform(mut self, platform: &str) -> DeviceOverride {
+        self.platform = Some(platform.to_string());
+        self
+    }
+
+    /// Only consider the device with this index, or whose name contains
+    /// this string.
+    pub fn device(mut self, device: &str) -> DeviceOverride {
+        self.device = Some(device.to_string());
+        self
+    }
+
+    /// Only consider devices of this type (`cpu`, `gpu`, `accelerator`,
+    /// `default`, `custom` or `all`).
+    pub fn device_type(mut self, device_type: &str) -> DeviceOverride {
+        self.device_type = Some(device_type.to_string());
+        self
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.platform.is_none() && self.device.is_none() && self.device_type.is_none()
+    }
+
+    /// The devices allowed by this override, in platform order. Fails if
+    /// any of the restrictions matches nothing.
+    pub fn devices(&self) -> Result<Vec<Device>, Error> {
+        let mut platforms = try!(get_platforms());
+        if platforms.is_empty() {
+            return Err(Error::new(CL_PLATFORM_NOT_FOUND_KHR as cl_int, "clGetPlatformIDs",
+                                  "No platform found"));
+        }
+
+        if let Some(ref wanted) = self.platform {
+            let names: Vec<String> = platforms.iter()
+                .map(|p| p.name().unwrap_or(String::new()))
+                .collect();
+            let index = match matching(wanted, &names[..]) {
+                Some(index) => index,
+                None => return Err(Error::new(CL_PLATFORM_NOT_FOUND_KHR as cl_int,
+                                              "clGetPlatformIDs",
+                                              &no_match("RUST_OPENCL_PLATFORM", wanted,
+                                                        "platform", &names[..])))
+            };
+            platforms = vec![platforms.swap_remove(index)];
+        }
+
+        let types = match self.device_type {
+            None => None,
+            Some(ref t) => match &t.to_ascii_lowercase()[..] {
+                "cpu" => Some(DeviceType::CPU),
+                "gpu" => Some(DeviceType::GPU),
+                "accelerator" => Some(DeviceType::Accelerator),
+                "default" => Some(DeviceType::Default),
+                "custom" => Some(DeviceType::Custom),
+                "all" => Some(DeviceType::All),
+                _ => return Err(Error::new(CL_INVALID_DEVICE_TYPE as cl_int, "clGetDeviceIDs",
+                                           &format!("RUST_OPENCL_DEVICE_TYPE={:?} is not one \
+                                                     of cpu, gpu, accelerator, default, \
+                                                     custom, all", t)))
+            }
+        };
+
+        let mut devices = Vec::new();
+        for platform in platforms.iter() {
+            let found = match types {
+                Some(t) => try!(platform.get_devices_by_types(&[t])),
+                None => try!(platform.get_devices())
+            };
+            devices.extend(found.into_iter());
+        }
+        if devices.is_empty() {
+            return Err(Error::new(CL_DEVICE_NOT_FOUND as cl_int, "clGetDeviceIDs",
+                                  &format!("No device matches RUST_OPENCL_PLATFORM={:?} \
+                                            RUST_OPENCL_DEVICE_TYPE={:?}",
+                                           self.platform, self.device_type)));
+        }
+
+        if let Some(ref wanted) = self.device {
+            let names: Vec<String> = devices.iter()
+                .map(|d| d.name().unwrap_or(String::new()))
+                .collect();
+            let index = match matching(wanted, &names[..]) {
+                Some(index) => index,
+                None => return Err(Error::new(CL_DEVICE_NOT_FOUND as cl_int, "clGetDeviceIDs",
+                                              &no_match("RUST_OPENCL_DEVICE", wanted,
+                                                        "device", &names[..])))
+            };
+            devices = vec![devices.swap_remove(index)];
+        }
+
+        Ok(devices)
+    }
+}
+
+/// Finds `wanted` in `names`, either as an index or as a case-insensitive
+/// substring.
+fn matching(wanted: &str, names: &[String]) -> Option<usize> {
+    if let Ok(index) = wanted.parse::<usize>() {
+        return if index < names.len() { Some(index) } else { None };
+    }
+    let wanted = wanted.to_lowercase();
+    names.iter().position(|name| name.to_lowercase().contains(&wanted[..]))
+}
+
+fn no_match(var: &str, wanted: &str, what: &str, names: &[String]) -> String {
+    let mut message = format!("{}={:?} matches no {}; available:", var, wanted, what);
+    for (i, name) in names.iter().enumerate() {
+        message.push_str(&format!("\n  {}: {}", i, name));
     }
+    message
 }
@@ -23,12 +23,12 @@ macro_rules! expect (
 pub fn test_all_platforms_devices<F>(test: &mut F)
     where F: FnMut(&Device, &Context, &CommandQueue)
 {
-    let platforms = get_platforms();
+    let platforms = get_platforms().unwrap();
     for p in platforms.iter() {
-        let devices = p.get_devices();
+        let devices = p.get_devices().unwrap();
         for d in devices.iter() {
-            let context = d.create_context();
-            let queue = context.create_command_queue(d);
+            let context = d.create_context().unwrap();
+            let queue = context.create_command_queue(d).unwrap();
             test(d, &context, &queue);
         }
     }
@@ -131,6 +131,10 @@ mod mem {
 #[cfg(test)]
 mod hl {
     use opencl::cl::*;
+    use opencl::cl::CLStatus::{CL_BUILD_PROGRAM_FAILURE, CL_INVALID_OPERATION, CL_INVALID_VALUE,
+                               CL_INVALID_QUEUE_PROPERTIES, CL_PROFILING_INFO_NOT_AVAILABLE,
+                               CL_INVALID_WORK_DIMENSION, CL_INVALID_WORK_GROUP_SIZE,
+                               CL_INVALID_WORK_ITEM_SIZE, CL_MEM_COPY_OVERLAP};
     use opencl::hl::*;
     use opencl::mem::*;
     use opencl::util;
@@ -141,28 +145,167 @@ mod hl {
                    *i += 1; \
                    }";
         ::test_all_platforms_devices(&mut |device, ctx, _| {
-            let prog = ctx.create_program_from_source(src);
+            let prog = ctx.create_program_from_source(src).unwrap();
             prog.build(device).unwrap();
         })
     }
 
+    #[test]
+    fn program_build_error() {
+        let src = "__kernel void test(__global int *i) { \
+                   *i += undefined_variable; \
+                   }";
+        ::test_all_platforms_devices(&mut |device, ctx, _| {
+            let prog = ctx.create_program_from_source(src).unwrap();
+            match prog.build_with_options(&[*device], "-DFOO=1") {
+                Ok(_) => panic!("program with an undefined variable built"),
+                Err(e) => {
+                    expect!(e.error.status(), Some(CL_BUILD_PROGRAM_FAILURE));
+                    expect!(&e.options[..], "-DFOO=1");
+                    let &(status, _) = e.devices.get(device).unwrap();
+                    expect!(status, BuildStatus::Error);
+                }
+            }
+        })
+    }
+
+    #[test]
+    fn program_build_all_devices() {
+        let src = "__kernel void test(__global int *i) { \
+                   *i += 1; \
+                   }";
+        for platform in get_platforms().unwrap().iter() {
+            let devices = platform.get_devices().unwrap();
+            if devices.is_empty() {
+                continue;
+            }
+            let ctx = create_context_with_properties(&devices[..], &[0]).unwrap();
+            let prog = ctx.create_program_from_source(src).unwrap();
+            let logs = prog.build_with_options(&devices[..], "").unwrap();
+            expect!(logs.len(), devices.len());
+        }
+    }
+
+    #[test]
+    fn multi_device_context() {
+        let src = "__kernel void test(__global int *i) { \
+                   *i += 1; \
+                   }";
+        for platform in get_platforms().unwrap().iter() {
+            let devices = platform.get_devices().unwrap();
+            if devices.is_empty() {
+                continue;
+            }
+            let ctx = Context::new(&devices[..], ContextProperties::new()).unwrap();
+            expect!(ctx.devices().unwrap(), devices.clone());
+
+            let prog = ctx.create_program_from_source(src).unwrap();
+            prog.build_with_options(&[], "").unwrap();
+            let k = prog.create_kernel("test").unwrap();
+            let v = ctx.create_buffer_from(vec![1isize], CL_MEM_READ_WRITE).unwrap();
+            k.set_arg(0, &v).unwrap();
+
+            let queues = ctx.create_command_queues().unwrap();
+            expect!(queues.len(), devices.len());
+            for queue in queues.iter() {
+                queue.enqueue_async_kernel(&k, 1isize, None, ()).unwrap().wait().unwrap();
+            }
+            let v: Vec<isize> = queues[0].get(&v, ()).unwrap();
+            expect!(v[0], 1 + devices.len() as isize);
+        }
+
+        let e = Context::new(&[], ContextProperties::new()).err().unwrap();
+        expect!(e.status(), Some(CL_INVALID_VALUE));
+        expect!(create_context_with_properties(&[], &[]).is_err(), true);
+    }
+
+    #[test]
+    fn context_properties() {
+        for platform in get_platforms().unwrap().iter() {
+            let devices = platform.get_devices().unwrap();
+            if devices.is_empty() {
+                continue;
+            }
+            let props = ContextProperties::new().platform(platform);
+            expect!(props.get(CL_CONTEXT_PLATFORM), Some(platform.get_id() as cl_context_properties));
+            expect!(props.clone().platform(platform), props.clone());
+
+            let ctx = Context::from_type(DeviceType::All, props).unwrap();
+            expect!(ctx.devices().unwrap().len(), devices.len());
+        }
+    }
+
+    #[test]
+    fn context_notify_lifetime() {
+        use std::sync::Arc;
+
+        for platform in get_platforms().unwrap().iter() {
+            let devices = platform.get_devices().unwrap();
+            if devices.is_empty() {
+                continue;
+            }
+            let token = Arc::new(());
+            let held = token.clone();
+            let ctx = Context::with_notify(&devices[..1], ContextProperties::new(),
+                                           move |msg, _| error!("{} {:?}", msg, held)).unwrap();
+            expect!(Arc::strong_count(&token), 2);
+            drop(ctx);
+            expect!(Arc::strong_count(&token), 1);
+        }
+    }
+
+    #[test]
+    fn object_info() {
+        let src = "__kernel void test(__global int *i, int j) { \
+                   *i += j; \
+                   }";
+        ::test_all_platforms_devices(&mut |device, ctx, queue| {
+            expect!(ctx.reference_count().unwrap() >= 1, true);
+            expect!(ctx.devices().unwrap(), vec![*device]);
+            expect!(ctx.properties().unwrap().get(CL_CONTEXT_PLATFORM),
+                    Some(device.platform().unwrap().get_id() as cl_context_properties));
+
+            expect!(queue.device().unwrap(), *device);
+            expect!(queue.context().unwrap().ctx, ctx.ctx);
+            expect!(queue.properties().unwrap().profiling, true);
+
+            let prog = ctx.create_program_from_source(src).unwrap();
+            prog.build(device).unwrap();
+            expect!(prog.source().unwrap(), src.to_string());
+            expect!(prog.devices().unwrap(), vec![*device]);
+            expect!(prog.num_kernels().unwrap(), 1);
+            expect!(prog.kernel_names().unwrap(), vec!["test".to_string()]);
+
+            let k = prog.create_kernel("test").unwrap();
+            expect!(k.function_name().unwrap(), "test".to_string());
+            expect!(k.num_args().unwrap(), 2);
+            expect!(k.program().unwrap().source().unwrap(), src.to_string());
+
+            let buf: CLBuffer<i32> = ctx.create_buffer(16, CL_MEM_READ_ONLY).unwrap();
+            expect!(buf.flags().unwrap(), CL_MEM_READ_ONLY);
+            expect!(buf.size().unwrap(), 16 * 4);
+            expect!(buf.map_count().unwrap(), 0);
+            expect!(buf.host_ptr().unwrap().is_null(), true);
+        })
+    }
+
     #[test]
     fn simple_kernel() {
         let src = "__kernel void test(__global int *i) { \
                    *i += 1; \
                    }";
         ::test_all_platforms_devices(&mut |device, ctx, queue| {
-            let prog = ctx.create_program_from_source(src);
+            let prog = ctx.create_program_from_source(src).unwrap();
             prog.build(device).unwrap();
 
-            let k = prog.create_kernel("test");
-            let v = ctx.create_buffer_from(vec![1isize], CL_MEM_READ_WRITE);
+            let k = prog.create_kernel("test").unwrap();
+            let v = ctx.create_buffer_from(vec![1isize], CL_MEM_READ_WRITE).unwrap();
 
-            k.set_arg(0, &v);
+            k.set_arg(0, &v).unwrap();
 
-            queue.enqueue_async_kernel(&k, 1isize, None, ()).wait();
+            queue.enqueue_async_kernel(&k, 1isize, None, ()).unwrap().wait().unwrap();
 
-            let v: Vec<isize> = queue.get(&v, ());
+            let v: Vec<isize> = queue.get(&v, ()).unwrap();
 
             expect!(v[0], 2);
         })
@@ -175,19 +318,19 @@ mod hl {
                    }";
 
         ::test_all_platforms_devices(&mut |device, ctx, queue| {
-            let prog = ctx.create_program_from_source(src);
+            let prog = ctx.create_program_from_source(src).unwrap();
             prog.build(device).unwrap();
 
-            let k = prog.create_kernel("test");
+            let k = prog.create_kernel("test").unwrap();
 
-            let v = ctx.create_buffer_from(vec![1isize], CL_MEM_READ_WRITE);
+            let v = ctx.create_buffer_from(vec![1isize], CL_MEM_READ_WRITE).unwrap();
 
-            k.set_arg(0, &v);
-            k.set_arg(1, &42isize);
+            k.set_arg(0, &v).unwrap();
+            k.set_arg(1, &42isize).unwrap();
 
-            queue.enqueue_async_kernel(&k, 1isize, None, ()).wait();
+            queue.enqueue_async_kernel(&k, 1isize, None, ()).unwrap().wait().unwrap();
 
-            let v: Vec<isize> = queue.get(&v, ());
+            let v: Vec<isize> = queue.get(&v, ()).unwrap();
 
             expect!(v[0], 43);
         })
@@ -200,18 +343,18 @@ mod hl {
                    }";
 
         ::test_all_platforms_devices(&mut |device, ctx, queue| {
-            let prog = ctx.create_program_from_source(src);
+            let prog = ctx.create_program_from_source(src).unwrap();
             prog.build(device).unwrap();
 
-            let k = prog.create_kernel("test");
+            let k = prog.create_kernel("test").unwrap();
 
-            let v = ctx.create_buffer_from(vec![1isize], CL_MEM_READ_WRITE);
+            let v = ctx.create_buffer_from(vec![1isize], CL_MEM_READ_WRITE).unwrap();
 
-            k.set_arg(0, &v);
+            k.set_arg(0, &v).unwrap();
 
-            queue.enqueue_async_kernel(&k, 1isize, None, ()).wait();
+            queue.enqueue_async_kernel(&k, 1isize, None, ()).unwrap().wait().unwrap();
 
-            let v: Vec<isize> = queue.get(&v, ());
+            let v: Vec<isize> = queue.get(&v, ()).unwrap();
 
             expect!(v[0], 2);
         })
@@ -224,21 +367,21 @@ mod hl {
                    }";
 
         ::test_all_platforms_devices(&mut |device, ctx, queue| {
-            let prog = ctx.create_program_from_source(src);
+            let prog = ctx.create_program_from_source(src).unwrap();
             prog.build(device).unwrap();
 
-            let k = prog.create_kernel("test");
-            let v = ctx.create_buffer_from(vec![1isize], CL_MEM_READ_WRITE);
+            let k = prog.create_kernel("test").unwrap();
+            let v = ctx.create_buffer_from(vec![1isize], CL_MEM_READ_WRITE).unwrap();
 
-            k.set_arg(0, &v);
+            k.set_arg(0, &v).unwrap();
 
             let mut e : Option<Event> = None;
             for _ in 0isize .. 8 {
-                e = Some(queue.enqueue_async_kernel(&k, 1isize, None, e));
+                e = Some(queue.enqueue_async_kernel(&k, 1isize, None, e).unwrap());
             }
-            e.wait();
+            e.wait().unwrap();
 
-            let v: Vec<isize> = queue.get(&v, ());
+            let v: Vec<isize> = queue.get(&v, ()).unwrap();
 
             expect!(v[0], 9);
         })
@@ -254,32 +397,32 @@ mod hl {
                    }";
 
         ::test_all_platforms_devices(&mut |device, ctx, queue| {
-            let prog = ctx.create_program_from_source(src);
+            let prog = ctx.create_program_from_source(src).unwrap();
             prog.build(device).unwrap();
 
-            let k_inc_a = prog.create_kernel("inc");
-            let k_inc_b = prog.create_kernel("inc");
-            let k_add = prog.create_kernel("add");
+            let k_inc_a = prog.create_kernel("inc").unwrap();
+            let k_inc_b = prog.create_kernel("inc").unwrap();
+            let k_add = prog.create_kernel("add").unwrap();
 
-            let a = ctx.create_buffer_from(vec![1isize], CL_MEM_READ_WRITE);
-            let b = ctx.create_buffer_from(vec![1isize], CL_MEM_READ_WRITE);
-            let c = ctx.create_buffer_from(vec![1isize], CL_MEM_READ_WRITE);
+            let a = ctx.create_buffer_from(vec![1isize], CL_MEM_READ_WRITE).unwrap();
+            let b = ctx.create_buffer_from(vec![1isize], CL_MEM_READ_WRITE).unwrap();
+            let c = ctx.create_buffer_from(vec![1isize], CL_MEM_READ_WRITE).unwrap();
 
-            k_inc_a.set_arg(0, &a);
-            k_inc_b.set_arg(0, &b);
+            k_inc_a.set_arg(0, &a).unwrap();
+            k_inc_b.set_arg(0, &b).unwrap();
 
             let event_list = [
-                queue.enqueue_async_kernel(&k_inc_a, 1isize, None, ()),
-                queue.enqueue_async_kernel(&k_inc_b, 1isize, None, ()),
+                queue.enqueue_async_kernel(&k_inc_a, 1isize, None, ()).unwrap(),
+                queue.enqueue_async_kernel(&k_inc_b, 1isize, None, ()).unwrap(),
             ];
 
-            k_add.set_arg(0, &a);
-            k_add.set_arg(1, &b);
-            k_add.set_arg(2, &c);
+            k_add.set_arg(0, &a).unwrap();
+            k_add.set_arg(1, &b).unwrap();
+            k_add.set_arg(2, &c).unwrap();
 
-            let event = queue.enqueue_async_kernel(&k_add, 1isize, None, &event_list[..]);
+            let event = queue.enqueue_async_kernel(&k_add, 1isize, None, &event_list[..]).unwrap();
 
-            let v: Vec<isize> = queue.get(&c, event);
+            let v: Vec<isize> = queue.get(&c, event).unwrap();
 
             expect!(v[0], 4);
         })
@@ -295,7 +438,7 @@ mod hl {
                    N[i * s + j] = i * j;
 }";
         ::test_all_platforms_devices(&mut |device, ctx, queue| {
-            let prog = ctx.create_program_from_source(src);
+            let prog = ctx.create_program_from_source(src).unwrap();
 
             match prog.build(device) {
                 Ok(_) => (),
@@ -306,15 +449,15 @@ mod hl {
                 }
             }
 
-            let k = prog.create_kernel("test");
+            let k = prog.create_kernel("test").unwrap();
 
-            let v = ctx.create_buffer_from(&[1isize, 2, 3, 4, 5, 6, 7, 8, 9][..], CL_MEM_READ_ONLY);
+            let v = ctx.create_buffer_from(&[1isize, 2, 3, 4, 5, 6, 7, 8, 9][..], CL_MEM_READ_ONLY).unwrap();
 
-            k.set_arg(0, &v);
+            k.set_arg(0, &v).unwrap();
 
-            queue.enqueue_async_kernel(&k, (3isize, 3isize), None, ()).wait();
+            queue.enqueue_async_kernel(&k, (3isize, 3isize), None, ()).unwrap().wait().unwrap();
 
-            let v: Vec<isize> = queue.get(&v, ());
+            let v: Vec<isize> = queue.get(&v, ()).unwrap();
 
             expect!(v, vec!(0, 0, 0, 0, 1, 2, 0, 2, 4));
         })
@@ -324,13 +467,13 @@ mod hl {
     fn memory_read_write()
     {
         ::test_all_platforms_devices(&mut |_, ctx, queue| {
-            let buffer: CLBuffer<isize> = ctx.create_buffer(8, CL_MEM_READ_ONLY);
+            let buffer: CLBuffer<isize> = ctx.create_buffer(8, CL_MEM_READ_ONLY).unwrap();
 
             let input = [0isize, 1, 2, 3, 4, 5, 6, 7];
             let mut output = [0isize, 0, 0, 0, 0, 0, 0, 0];
 
-            queue.write(&buffer, &&input[..], ());
-            queue.read(&buffer, &mut &mut output[..], ());
+            queue.write(&buffer, &&input[..], ()).unwrap();
+            queue.read(&buffer, &mut &mut output[..], ()).unwrap();
 
             expect!(input, output);
         })
@@ -341,8 +484,8 @@ mod hl {
     {
         ::test_all_platforms_devices(&mut |_, ctx, queue| {
             let input = [0isize, 1, 2, 3, 4, 5, 6, 7];
-            let buffer = ctx.create_buffer_from(&input[..], CL_MEM_READ_WRITE);
-            let output: Vec<isize> = queue.get(&buffer, ());
+            let buffer = ctx.create_buffer_from(&input[..], CL_MEM_READ_WRITE).unwrap();
+            let output: Vec<isize> = queue.get(&buffer, ()).unwrap();
             expect!(&input[..], &output[..]);
         })
     }
@@ -353,8 +496,8 @@ mod hl {
     {
         ::test_all_platforms_devices(&mut |_, ctx, queue| {
             let input = vec!(0isize, 1, 2, 3, 4, 5, 6, 7);
-            let buffer = ctx.create_buffer_from(&input, CL_MEM_READ_WRITE);
-            let output: Vec<isize> = queue.get(&buffer, ());
+            let buffer = ctx.create_buffer_from(&input, CL_MEM_READ_WRITE).unwrap();
+            let output: Vec<isize> = queue.get(&buffer, ()).unwrap();
             expect!(input, output);
         })
     }
@@ -364,12 +507,158 @@ mod hl {
     {
         ::test_all_platforms_devices(&mut |_, ctx, queue| {
             let input = vec!(0isize, 1, 2, 3, 4, 5, 6, 7);
-            let buffer = ctx.create_buffer_from(input.clone(), CL_MEM_READ_WRITE);
-            let output: Vec<isize> = queue.get(&buffer, ());
+            let buffer = ctx.create_buffer_from(input.clone(), CL_MEM_READ_WRITE).unwrap();
+            let output: Vec<isize> = queue.get(&buffer, ()).unwrap();
             expect!(input, output);
         })
     }
 
+    #[test]
+    fn device_info() {
+        ::test_all_platforms_devices(&mut |device, _, _| {
+            let dims = device.max_work_item_dimensions().unwrap();
+            expect!(dims >= 3, true);
+            expect!(device.max_work_item_sizes().unwrap().len(), dims as usize);
+            expect!(device.max_work_group_size().unwrap() >= 1, true);
+            expect!(device.max_compute_units().unwrap() >= 1, true);
+            expect!(device.global_mem_size().unwrap() >= device.max_mem_alloc_size().unwrap(), true);
+            let bits = device.address_bits().unwrap();
+            expect!(bits == 32 || bits == 64, true);
+            expect!(device.available().unwrap(), true);
+            expect!(device.execution_capabilities().unwrap().kernel, true);
+            expect!(device.single_fp_config().unwrap().round_to_nearest, true);
+            expect!(device.name().unwrap().ends_with("\0"), false);
+            expect!(device.platform().unwrap().get_id() != ::std::ptr::null_mut(), true);
+        })
+    }
+
+    #[test]
+    fn device_types() {
+        let types = [DeviceType::CPU, DeviceType::GPU, DeviceType::Accelerator,
+                     DeviceType::Custom];
+        for platform in get_platforms().unwrap().iter() {
+            let all = platform.get_devices_by_types(&[DeviceType::All]).unwrap();
+            expect!(all.clone(), platform.get_devices().unwrap());
+            for device in all.iter() {
+                let decoded = device.device_type().unwrap();
+                expect!(decoded.contains(&DeviceType::All), false);
+                for &t in types.iter() {
+                    let listed = platform.get_devices_by_types(&[t]).unwrap().contains(device);
+                    expect!(decoded.contains(&t), listed);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn device_partition() {
+        ::test_all_platforms_devices(&mut |device, _, _| {
+            expect!(device.parent_device().unwrap(), None);
+            expect!(device.partition_type().unwrap(), None);
+
+            let kinds = device.partition_kinds().unwrap();
+            expect!(kinds.contains(&PartitionKind::ByAffinityDomain),
+                    !device.partition_affinity_domains().unwrap().is_empty());
+            if !kinds.contains(&PartitionKind::Equally) || device.max_compute_units().unwrap() < 2 {
+                if kinds.is_empty() {
+                    expect!(device.partition_equally(1).is_err(), true);
+                }
+                return;
+            }
+
+            let subs = device.partition_equally(1).unwrap();
+            expect!(subs.is_empty(), false);
+            for sub in subs.iter() {
+                expect!(sub.parent_device().unwrap(), Some(*device));
+                expect!(sub.max_compute_units().unwrap(), 1);
+                expect!(sub.partition_type().unwrap(), Some(PartitionType::Equally(1)));
+            }
+
+            if kinds.contains(&PartitionKind::ByCounts) {
+                let subs = device.partition_by_counts(&[1, 1]).unwrap();
+                expect!(subs.len(), 2);
+                expect!(subs[0].partition_type().unwrap(),
+                        Some(PartitionType::ByCounts(vec![1, 1])));
+            }
+        })
+    }
+
+    #[test]
+    fn partition_kinds() {
+        use opencl::ext::cl_ext_device_fission::CL_DEVICE_PARTITION_BY_AFFINITY_DOMAIN_EXT;
+
+        expect!(PartitionKind::from_cl(CL_DEVICE_PARTITION_BY_AFFINITY_DOMAIN),
+                Some(PartitionKind::ByAffinityDomain));
+        expect!(PartitionKind::from_ext(CL_DEVICE_PARTITION_BY_AFFINITY_DOMAIN_EXT),
+                Some(PartitionKind::ByAffinityDomain));
+        expect!(PartitionKind::from_cl(CL_DEVICE_PARTITION_EQUALLY),
+                Some(PartitionKind::Equally));
+        expect!(PartitionKind::from_cl(0), None);
+    }
+
+    #[test]
+    fn extension_sets() {
+        for platform in get_platforms().unwrap().iter() {
+            let set = platform.extensions_set().unwrap();
+            expect!(set.len(), platform.extensions().unwrap().split_whitespace().count());
+            for ext in set.iter() {
+                expect!(platform.supports(ext).unwrap(), true);
+                expect!(platform.supports(&ext[..ext.len() - 1]).unwrap(),
+                        set.contains(&ext[..ext.len() - 1]));
+            }
+        }
+        ::test_all_platforms_devices(&mut |device, _, _| {
+            let set = device.extensions_set().unwrap();
+            expect!(set.contains(""), false);
+            expect!(device.supports("cl_khr_fp64").unwrap(), set.contains("cl_khr_fp64"));
+            expect!(device.supports("cl_khr_fp6").unwrap(), false);
+        })
+    }
+
+    #[test]
+    fn vendor_attributes() {
+        ::test_all_platforms_devices(&mut |device, _, _| {
+            if device.supports("cl_nv_device_attribute_query").unwrap() {
+                expect!(device.nv_compute_capability().unwrap().0 >= 1, true);
+                expect!(device.nv_warp_size().unwrap() >= 1, true);
+            } else {
+                let e = device.nv_warp_size().err().unwrap();
+                expect!(e.status(), Some(CL_INVALID_OPERATION));
+            }
+            if !device.supports("cl_amd_device_attribute_query").unwrap() {
+                let e = device.amd_profiling_timer_offset().err().unwrap();
+                expect!(e.status(), Some(CL_INVALID_OPERATION));
+            }
+        })
+    }
+
+    #[test]
+    fn version_parse() {
+        expect!(ClVersion::parse("OpenCL 1.2 pocl 1.8"), Some(ClVersion::new(1, 2)));
+        expect!(ClVersion::parse("OpenCL 1.1 "), Some(ClVersion::new(1, 1)));
+        expect!(ClVersion::parse("OpenCL C 1.2 "), Some(ClVersion::new(1, 2)));
+        expect!(ClVersion::parse("OpenCL 2.0 AMD-APP (1800.8)"), Some(ClVersion::new(2, 0)));
+        expect!(ClVersion::parse("CUDA 1.0"), None);
+        expect!(ClVersion::parse("OpenCL"), None);
+        expect!(ClVersion::new(1, 2) > ClVersion::new(1, 1), true);
+        expect!(ClVersion::new(2, 0) > ClVersion::new(1, 2), true);
+    }
+
+    #[test]
+    fn version_require() {
+        ::test_all_platforms_devices(&mut |device, _, _| {
+            let version = device.cl_version().unwrap();
+            expect!(version <= device.platform().unwrap().cl_version().unwrap(), true);
+            device.cl_c_version().unwrap();
+            device.require_version(version, "clTest").unwrap();
+
+            let newer = ClVersion::new(version.major + 1, 0);
+            let e = device.require_version(newer, "clTest").unwrap_err();
+            expect!(e.status(), Some(CL_INVALID_OPERATION));
+            expect!(e.message().contains(&format!("requires OpenCL {}", newer)), true);
+        })
+    }
+
     #[test]
     fn event_get_times() {
         let src = "__kernel void test(__global int *i) { \
@@ -377,39 +666,453 @@ mod hl {
                    }";
 
         let (device, ctx, queue) = util::create_compute_context().unwrap();
-        let prog = ctx.create_program_from_source(src);
+        let prog = ctx.create_program_from_source(src).unwrap();
         prog.build(&device).unwrap();
 
-        let k = prog.create_kernel("test");
-        let v = ctx.create_buffer_from(vec![1isize], CL_MEM_READ_WRITE);
+        let k = prog.create_kernel("test").unwrap();
+        let v = ctx.create_buffer_from(vec![1isize], CL_MEM_READ_WRITE).unwrap();
 
-        k.set_arg(0, &v);
+        k.set_arg(0, &v).unwrap();
 
-        let e = queue.enqueue_async_kernel(&k, 1isize, None, ());
-        e.wait();
+        let e = queue.enqueue_async_kernel(&k, 1isize, None, ()).unwrap();
+        e.wait().unwrap();
 
         // the that are returned are not useful for unit test, this test
         // is mostly testing that opencl returns no error
-        e.queue_time();
-        e.submit_time();
-        e.start_time();
-        e.end_time();
+        e.queue_time().unwrap();
+        e.submit_time().unwrap();
+        e.start_time().unwrap();
+        e.end_time().unwrap();
+    }
+
+    #[test]
+    fn event_times_without_profiling() {
+        let src = "__kernel void test(__global int *i) { \
+                   *i += 1; \
+                   }";
+
+        ::test_all_platforms_devices(&mut |device, ctx, _| {
+            let queue = ctx.create_command_queue_with_properties(device, QueueProperties::new())
+                           .unwrap();
+            expect!(queue.properties().unwrap(), QueueProperties::new());
+
+            let prog = ctx.create_program_from_source(src).unwrap();
+            prog.build(device).unwrap();
+            let k = prog.create_kernel("test").unwrap();
+            let v = ctx.create_buffer_from(vec![1isize], CL_MEM_READ_WRITE).unwrap();
+            k.set_arg(0, &v).unwrap();
+
+            let e = queue.enqueue_async_kernel(&k, 1isize, None, ()).unwrap();
+            e.wait().unwrap();
+            let err = e.start_time().err().unwrap();
+            expect!(err.status(), Some(CL_PROFILING_INFO_NOT_AVAILABLE));
+            expect!(err.message().contains("without profiling"), true);
+        })
+    }
+
+    #[test]
+    fn ndrange_offset() {
+        let src = "__kernel void test(__global int *out) { \
+                   size_t i = get_global_id(0); \
+                   out[i] = (int) i; \
+                   }";
+
+        ::test_all_platforms_devices(&mut |device, ctx, queue| {
+            let prog = ctx.create_program_from_source(src).unwrap();
+            prog.build(device).unwrap();
+            let k = prog.create_kernel("test").unwrap();
+            let v = ctx.create_buffer_from(vec![0i32; 16], CL_MEM_READ_WRITE).unwrap();
+            k.set_arg(0, &v).unwrap();
+
+            queue.enqueue_ndrange(&k, [8], ()).unwrap();
+            queue.enqueue_ndrange(&k, NDRange::new(&[8]).offset(&[8]).local(&[1]), ()).unwrap();
+            let v: Vec<i32> = queue.get(&v, ()).unwrap();
+            expect!(v, (0..16).collect::<Vec<i32>>());
+        })
+    }
+
+    #[test]
+    fn ndrange_validation() {
+        expect!(NDRange::from([4, 2]), NDRange::new(&[4, 2]));
+        expect!(NDRange::from((4, 2, 1)).offset, vec![0, 0, 0]);
+
+        ::test_all_platforms_devices(&mut |device, ctx, queue| {
+            expect!(NDRange::new(&[8, 8]).local(&[2, 2]).validate(device).is_ok(), true);
+
+            let e = NDRange::new(&[1, 1, 1, 1, 1, 1, 1, 1]).validate(device).err().unwrap();
+            expect!(e.status(), Some(CL_INVALID_WORK_DIMENSION));
+            let e = NDRange::new(&[8, 8]).offset(&[0]).validate(device).err().unwrap();
+            expect!(e.status(), Some(CL_INVALID_WORK_DIMENSION));
+            let e = NDRange::new(&[8]).local(&[3]).validate(device).err().unwrap();
+            expect!(e.status(), Some(CL_INVALID_WORK_GROUP_SIZE));
+
+            let max = device.max_work_item_sizes().unwrap()[0] + 1;
+            let e = NDRange::new(&[max]).local(&[max]).validate(device).err().unwrap();
+            expect!(e.status(), Some(CL_INVALID_WORK_ITEM_SIZE));
+
+            // The index-based entry points are checked the same way.
+            let prog = ctx.create_program_from_source("__kernel void test() {}").unwrap();
+            prog.build(device).unwrap();
+            let k = prog.create_kernel("test").unwrap();
+            let e = queue.enqueue_async_kernel(&k, 8isize, Some(3isize), ()).err().unwrap();
+            expect!(e.status(), Some(CL_INVALID_WORK_GROUP_SIZE));
+        })
+    }
+
+    #[test]
+    fn async_transfers() {
+        ::test_all_platforms_devices(&mut |_, ctx, queue| {
+            let buf: CLBuffer<i32> = ctx.create_buffer(64, CL_MEM_READ_WRITE).unwrap();
+
+            let written = queue.write_async(&buf, (0..64).collect::<Vec<i32>>(), ()).unwrap();
+            let read = queue.read_async(&buf, vec![0i32; 64], written.event()).unwrap();
+            expect!(written.wait().unwrap().len(), 64);
+            expect!(read.wait().unwrap(), (0..64).collect::<Vec<i32>>());
+
+            // Dropping a transfer waits for it rather than freeing its memory.
+            drop(queue.write_async(&buf, vec![7i32; 64], ()).unwrap());
+            let v: Vec<i32> = queue.get(&buf, ()).unwrap();
+            expect!(v, vec![7i32; 64]);
+        })
+    }
+
+    #[test]
+    fn buffer_copy() {
+        ::test_all_platforms_devices(&mut |_, ctx, queue| {
+            let src = ctx.create_buffer_from((0..16).collect::<Vec<i32>>(), CL_MEM_READ_WRITE)
+                         .unwrap();
+            let dst = ctx.create_buffer_from(vec![0i32; 8], CL_MEM_READ_WRITE).unwrap();
+
+            queue.copy(&src, 4..8, &dst, 2, ()).unwrap().wait().unwrap();
+            let v: Vec<i32> = queue.get(&dst, ()).unwrap();
+            expect!(v, vec![0, 0, 4, 5, 6, 7, 0, 0]);
+
+            expect!(queue.copy(&src, 10..17, &dst, 0, ()).err().unwrap().status(),
+                    Some(CL_INVALID_VALUE));
+            expect!(queue.copy(&src, 0..8, &dst, 1, ()).err().unwrap().status(),
+                    Some(CL_INVALID_VALUE));
+            expect!(queue.copy(&src, 0..8, &src, 4, ()).err().unwrap().status(),
+                    Some(CL_MEM_COPY_OVERLAP));
+
+            // An empty range copies nothing, even at the end of the buffer.
+            queue.copy(&src, 3..3, &dst, 8, ()).unwrap().wait().unwrap();
+            let v: Vec<i32> = queue.get(&dst, ()).unwrap();
+            expect!(v, vec![0, 0, 4, 5, 6, 7, 0, 0]);
+        })
+    }
+
+    #[test]
+    fn mapped_buffers() {
+        ::test_all_platforms_devices(&mut |_, ctx, queue| {
+            let mut buf = ctx.create_buffer_from((0..8).collect::<Vec<i32>>(),
+                                                 CL_MEM_READ_WRITE).unwrap();
+
+            {
+                let view = queue.map_read(&buf, 2..6).unwrap();
+                let first = queue.map_read(&buf, 0..1).unwrap();
+                expect!(&view[..], &[2, 3, 4, 5][..]);
+                expect!(&first[..], &[0][..]);
+            }
+
+            {
+                let mut view = queue.map_write(&mut buf, 0..2).unwrap();
+                expect!(view.len(), 2);
+                view.write(0, &[10, 11]).unwrap();
+                expect!(view.write(1, &[12, 13]).err().unwrap().status(), Some(CL_INVALID_VALUE));
+                view.unmap().unwrap().wait().unwrap();
+            }
+
+            {
+                let mut view = queue.map(&mut buf, 6..8).unwrap();
+                view[1] += 10;
+            }
+
+            let v: Vec<i32> = queue.get(&buf, ()).unwrap();
+            expect!(v, vec![10, 11, 2, 3, 4, 5, 6, 17]);
+
+            expect!(queue.map(&mut buf, 4..9).err().unwrap().status(), Some(CL_INVALID_VALUE));
+        })
+    }
+
+    #[test]
+    fn buffer_fill() {
+        ::test_all_platforms_devices(&mut |_, ctx, queue| {
+            let buf = ctx.create_buffer_from(vec![0i32; 8], CL_MEM_READ_WRITE).unwrap();
+
+            queue.fill(&buf, 7i32, 2..6).unwrap().wait().unwrap();
+            let v: Vec<i32> = queue.get(&buf, ()).unwrap();
+            expect!(v, vec![0, 0, 7, 7, 7, 7, 0, 0]);
+
+            let rgb = ctx.create_buffer_from(vec![[0u8; 3]; 4], CL_MEM_READ_WRITE).unwrap();
+            queue.fill(&rgb, [1u8, 2, 3], 1..3).unwrap().wait().unwrap();
+            let v: Vec<[u8; 3]> = queue.get(&rgb, ()).unwrap();
+            expect!(v, vec![[0, 0, 0], [1, 2, 3], [1, 2, 3], [0, 0, 0]]);
+            queue.fill(&rgb, [4u8, 5, 6], 3..4).unwrap().wait().unwrap();
+            let v: Vec<[u8; 3]> = queue.get(&rgb, ()).unwrap();
+            expect!(v, vec![[0, 0, 0], [1, 2, 3], [1, 2, 3], [4, 5, 6]]);
+
+            queue.fill(&buf, 1i32, 8..8).unwrap().wait().unwrap();
+            expect!(queue.fill(&buf, 0i32, 4..9).err().unwrap().status(),
+                    Some(CL_INVALID_VALUE));
+        })
+    }
+
+    #[test]
+    fn tasks_and_native_kernels() {
+        let src = "__kernel void test(__global int *i) { \
+                   *i *= 2; \
+                   }";
+
+        ::test_all_platforms_devices(&mut |device, ctx, queue| {
+            let prog = ctx.create_program_from_source(src).unwrap();
+            prog.build(device).unwrap();
+            let k = prog.create_kernel("test").unwrap();
+            let v = ctx.create_buffer_from(vec![3i32], CL_MEM_READ_WRITE).unwrap();
+            k.set_arg(0, &v).unwrap();
+
+            let task = queue.enqueue_task(&k, ()).unwrap();
+            let w = ctx.create_buffer_from(vec![1i32, 2], CL_MEM_READ_WRITE).unwrap();
+            let native = queue.enqueue_native(&[&v, &w], |buffers| {
+                buffers[1][1] += buffers[0][0];
+            }, &task);
+
+            if device.execution_capabilities().unwrap().native_kernel {
+                native.unwrap().wait().unwrap();
+                let r: Vec<i32> = queue.get(&w, ()).unwrap();
+                expect!(r, vec![1, 8]);
+
+                let panicked = queue.enqueue_native(&[&w], |_| panic!("native kernel"), ())
+                                    .unwrap();
+                panicked.wait().unwrap();
+            } else {
+                expect!(native.err().unwrap().status(), Some(CL_INVALID_OPERATION));
+            }
+
+            expect!(queue.enqueue_native(&[&v, &v], |_| (), ()).err().unwrap().status(),
+                    Some(if device.execution_capabilities().unwrap().native_kernel {
+                        CL_INVALID_VALUE
+                    } else {
+                        CL_INVALID_OPERATION
+                    }));
+        })
+    }
+
+    #[test]
+    fn user_events() {
+        let src = "__kernel void test(__global int *i) { \
+                   *i += 1; \
+                   }";
+
+        ::test_all_platforms_devices(&mut |device, ctx, queue| {
+            let prog = ctx.create_program_from_source(src).unwrap();
+            prog.build(device).unwrap();
+            let k = prog.create_kernel("test").unwrap();
+            let v = ctx.create_buffer_from(vec![1i32], CL_MEM_READ_WRITE).unwrap();
+            k.set_arg(0, &v).unwrap();
+
+            let gate = ctx.create_user_event().unwrap();
+            let e = queue.enqueue_async_kernel(&k, 1isize, None, &gate).unwrap();
+            queue.flush().unwrap();
+            let gate = ::std::thread::spawn(move || {
+                gate.complete().unwrap();
+                gate
+            }).join().unwrap();
+            e.wait().unwrap();
+            let r: Vec<i32> = queue.get(&v, ()).unwrap();
+            expect!(r, vec![2]);
+            expect!(gate.complete().err().unwrap().status(), Some(CL_INVALID_OPERATION));
+
+            let failed = ctx.create_user_event().unwrap();
+            expect!(failed.fail(0).err().unwrap().status(), Some(CL_INVALID_VALUE));
+            failed.fail(CL_INVALID_OPERATION as i32).unwrap();
+            expect!((&failed).wait().is_err(), true);
+        })
+    }
+
+    #[test]
+    fn event_callbacks() {
+        use std::sync::mpsc::channel;
+
+        ::test_all_platforms_devices(&mut |_, ctx, queue| {
+            let (tx, rx) = channel();
+            let marker = queue.marker().unwrap();
+            let done = tx.clone();
+            marker.on_complete(|_| panic!("event callback")).unwrap();
+            marker.on_complete(move |result| done.send(result.is_ok()).unwrap()).unwrap();
+            drop(marker);
+            queue.finish().unwrap();
+            expect!(rx.recv().unwrap(), true);
+
+            let gate = ctx.create_user_event().unwrap();
+            gate.event().on_complete(move |result| {
+                tx.send(result.is_ok()).unwrap()
+            }).unwrap();
+            gate.fail(CL_INVALID_OPERATION as i32).unwrap();
+            expect!(rx.recv().unwrap(), false);
+
+            expect!(gate.event().on_status(ExecutionStatus::Queued, |_| ()).err().unwrap().status(),
+                    Some(CL_INVALID_VALUE));
+        })
+    }
+
+    #[test]
+    fn event_status() {
+        use std::time::Duration;
+
+        ::test_all_platforms_devices(&mut |device, ctx, queue| {
+            let marker = queue.marker().unwrap();
+            expect!(marker.command_type().unwrap(), CommandType::Marker);
+            expect!(marker.queue().unwrap().unwrap().device().unwrap(), *device);
+            expect!(marker.wait_timeout(Duration::from_secs(10)).unwrap(), true);
+            expect!(marker.status().unwrap(), ExecutionStatus::Complete);
+
+            let gate = ctx.create_user_event().unwrap();
+            let event = gate.event();
+            expect!(event.command_type().unwrap(), CommandType::User);
+            expect!(event.queue().unwrap().is_none(), true);
+            expect!(event.status().unwrap(), ExecutionStatus::Submitted);
+            expect!(event.wait_timeout(Duration::from_millis(5)).unwrap(), false);
+            gate.fail(CL_INVALID_OPERATION as i32).unwrap();
+            expect!(event.status().unwrap(), ExecutionStatus::Error(CL_INVALID_OPERATION as i32));
+            expect!(event.wait_timeout(Duration::from_millis(5)).is_err(), true);
+        })
+    }
+
+    #[test]
+    fn queue_synchronization() {
+        let src = "__kernel void test(__global int *i) { \
+                   *i += 1; \
+                   }";
+
+        ::test_all_platforms_devices(&mut |device, ctx, queue| {
+            let prog = ctx.create_program_from_source(src).unwrap();
+            prog.build(device).unwrap();
+            let k = prog.create_kernel("test").unwrap();
+            let v = ctx.create_buffer_from(vec![1isize], CL_MEM_READ_WRITE).unwrap();
+            k.set_arg(0, &v).unwrap();
+
+            let first = queue.enqueue_async_kernel(&k, 1isize, None, ()).unwrap();
+            queue.wait_for(&first).unwrap();
+            queue.wait_for(()).unwrap();
+            queue.barrier().unwrap();
+            queue.enqueue_async_kernel(&k, 1isize, None, ()).unwrap();
+            let marker = queue.marker().unwrap();
+            queue.flush().unwrap();
+            marker.wait().unwrap();
+            queue.finish().unwrap();
+
+            let v: Vec<isize> = queue.get(&v, ()).unwrap();
+            expect!(v[0], 3);
+        })
+    }
+
+    #[test]
+    fn out_of_order_queue() {
+        ::test_all_platforms_devices(&mut |device, ctx, _| {
+            let props = QueueProperties { out_of_order: true, profiling: true };
+            let queue = ctx.create_command_queue_with_properties(device, props);
+            if device.queue_properties().unwrap().out_of_order {
+                expect!(queue.unwrap().properties().unwrap(), props);
+            } else {
+                expect!(queue.err().unwrap().status(), Some(CL_INVALID_QUEUE_PROPERTIES));
+            }
+        })
     }
 }
 
 
+#[cfg(test)]
+mod util {
+    use opencl::cl::CLStatus::{CL_DEVICE_NOT_FOUND, CL_INVALID_DEVICE_TYPE};
+    use opencl::hl::*;
+    use opencl::util::{DeviceSelector, DeviceOverride};
+
+    fn device_count() -> usize {
+        get_platforms().unwrap().iter().fold(0, |n, p| n + p.get_devices().unwrap().len())
+    }
+
+    #[test]
+    fn select_any() {
+        let selection = DeviceSelector::new().select().unwrap();
+        expect!(selection.devices.len(), device_count());
+        expect!(selection.rejected.len(), 0);
+    }
+
+    #[test]
+    fn select_ranked_by_type() {
+        let selection = DeviceSelector::new()
+            .device_type(DeviceType::CPU)
+            .device_type(DeviceType::GPU)
+            .select()
+            .unwrap();
+        let cpus = get_platforms().unwrap().iter()
+            .fold(0, |n, p| n + p.get_devices_by_types(&[DeviceType::CPU]).unwrap().len());
+        for device in selection.devices.iter().take(cpus) {
+            let cpu = get_platforms().unwrap().iter()
+                .any(|p| p.get_devices_by_types(&[DeviceType::CPU]).unwrap().contains(device));
+            expect!(cpu, true);
+        }
+    }
+
+    #[test]
+    fn select_none() {
+        let selector = DeviceSelector::new().min_compute_units(::std::u32::MAX);
+        let selection = selector.select().unwrap();
+        expect!(selection.devices.len(), 0);
+        expect!(selection.rejected.len(), device_count());
+
+        let e = selector.create_context().err().unwrap();
+        expect!(e.status(), Some(CL_DEVICE_NOT_FOUND));
+    }
+
+    #[test]
+    fn override_by_index_and_name() {
+        let first = get_platforms().unwrap()[0].get_devices().unwrap()[0];
+        let by_index = DeviceOverride::new().platform("0").device("0").devices().unwrap();
+        expect!(by_index, vec![first]);
+
+        let name = first.name().unwrap().to_uppercase();
+        let by_name = DeviceOverride::new().device(&name).devices().unwrap();
+        expect!(by_name.contains(&first), true);
+
+        let selection = DeviceSelector::new()
+            .device_override(DeviceOverride::new().platform("0").device("0"))
+            .select()
+            .unwrap();
+        expect!(selection.devices, vec![first]);
+    }
+
+    #[test]
+    fn override_no_match() {
+        let e = DeviceOverride::new().device("no such device, surely").devices().err().unwrap();
+        expect!(e.status(), Some(CL_DEVICE_NOT_FOUND));
+        expect!(e.message().contains("RUST_OPENCL_DEVICE"), true);
+
+        let e = DeviceOverride::new().device_type("fpga").devices().err().unwrap();
+        expect!(e.status(), Some(CL_INVALID_DEVICE_TYPE));
+
+        let e = DeviceSelector::new()
+            .device_override(DeviceOverride::new().device(&device_count().to_string()))
+            .select()
+            .err().unwrap();
+        expect!(e.status(), Some(CL_DEVICE_NOT_FOUND));
+    }
+}
+
 #[cfg(test)]
 mod array {
     use opencl::array::*;
     use opencl::cl::CL_MEM_READ_WRITE;
+    use opencl::cl::CLStatus::CL_MEM_COPY_OVERLAP;
+    use opencl::hl::EventList;
 
     #[test]
     fn put_get_2d()
     {
         ::test_all_platforms_devices(&mut |_, ctx, queue| {
             let arr_in = Array2D::new(8, 8, |x, y| {(x+y) as isize});
-            let arr_cl = ctx.create_buffer_from(&arr_in, CL_MEM_READ_WRITE);
-            let arr_out: Array2D<isize> = queue.get(&arr_cl, ());
+            let arr_cl = ctx.create_buffer_from(&arr_in, CL_MEM_READ_WRITE).unwrap();
+            let arr_out: Array2D<isize> = queue.get(&arr_cl, ()).unwrap();
 
             for x in 0usize.. 8usize {
                 for y in 0usize..8usize {
@@ -429,10 +1132,10 @@ mod array {
             let mut out = Array2D::new(8, 8, |_, _| {(0) as isize});
 
             /* both are zeroed */
-            let a_cl = ctx.create_buffer_from(&zero, CL_MEM_READ_WRITE);
+            let a_cl = ctx.create_buffer_from(&zero, CL_MEM_READ_WRITE).unwrap();
 
-            queue.write(&a_cl, &added, ());
-            queue.read(&a_cl, &mut out, ());
+            queue.write(&a_cl, &added, ()).unwrap();
+            queue.read(&a_cl, &mut out, ()).unwrap();
 
             for x in 0usize .. 8usize {
                 for y in 0usize .. 8usize {
@@ -443,13 +1146,61 @@ mod array {
     }
 
 
+    #[test]
+    fn rect_transfers()
+    {
+        ::test_all_platforms_devices(&mut |_, ctx, queue| {
+            let src = Array2D::new(8, 8, |x, y| {(x + 10*y) as i32});
+            let zero = Array3D::new(4, 4, 2, |_, _, _| {0 as i32});
+            let src_cl = ctx.create_buffer_from(&src, CL_MEM_READ_WRITE).unwrap();
+            let dst_cl = ctx.create_buffer_from(&zero, CL_MEM_READ_WRITE).unwrap();
+
+            // The 2x3 box at (5, 4) of src, to (1, 0) of the second slice of dst.
+            queue.copy_rect(&src_cl, [5, 4, 0], &dst_cl, [1, 0, 1], [2, 3, 1], ())
+                 .unwrap().wait().unwrap();
+            let dst: Array3D<i32> = queue.get(&dst_cl, ()).unwrap();
+            for x in 0usize .. 4usize {
+                for y in 0usize .. 4usize {
+                    let copied = x >= 1 && x < 3 && y < 3;
+                    expect!(dst.get(x, y, 0), 0);
+                    expect!(dst.get(x, y, 1), if copied { src.get(x + 4, y + 4) } else { 0 });
+                }
+            }
+
+            let mut out = Array2D::new(8, 8, |_, _| {0 as i32});
+            queue.read_rect(&src_cl, [2, 2, 0], &mut out, [0, 0, 0], [3, 3, 1], ()).unwrap();
+            expect!(out.get(0, 0), src.get(2, 2));
+            expect!(out.get(2, 1), src.get(4, 3));
+            expect!(out.get(3, 3), 0);
+
+            queue.write_rect(&out, [0, 0, 0], &src_cl, [5, 5, 0], [3, 3, 1], ()).unwrap();
+            let back: Array2D<i32> = queue.get(&src_cl, ()).unwrap();
+            expect!(back.get(5, 5), src.get(2, 2));
+            expect!(back.get(7, 7), src.get(4, 4));
+            expect!(back.get(4, 4), src.get(4, 4));
+
+            expect!(queue.read_rect(&src_cl, [6, 0, 0], &mut out, [0, 0, 0], [3, 1, 1], ())
+                         .is_err(), true);
+            expect!(queue.copy_rect(&src_cl, [0, 0, 0], &dst_cl, [0, 0, 0], [8, 1, 1], ())
+                         .is_err(), true);
+
+            // Within one buffer the boxes must not overlap.
+            expect!(queue.copy_rect(&src_cl, [0, 0, 0], &src_cl, [1, 1, 0], [2, 2, 1], ())
+                         .err().unwrap().status(), Some(CL_MEM_COPY_OVERLAP));
+            queue.copy_rect(&src_cl, [0, 0, 0], &src_cl, [2, 0, 0], [2, 2, 1], ())
+                 .unwrap().wait().unwrap();
+            let back: Array2D<i32> = queue.get(&src_cl, ()).unwrap();
+            expect!(back.get(2, 1), src.get(0, 1));
+        })
+    }
+
     #[test]
     fn kernel_2d()
     {
         ::test_all_platforms_devices(&mut |device, ctx, queue| {
             let mut a = Array2D::new(8, 8, |_, _| {(0) as i32});
             let b = Array2D::new(8, 8, |x, y| {(x*y) as i32});
-            let a_cl = ctx.create_buffer_from(&a, CL_MEM_READ_WRITE);
+            let a_cl = ctx.create_buffer_from(&a, CL_MEM_READ_WRITE).unwrap();
 
             let src =  "__kernel void test(__global int *a) { \
                             int x = get_global_id(0); \
@@ -457,7 +1208,7 @@ mod array {
                             int size_x = get_global_size(0); \
                             a[size_x*y + x] = x*y; \
                         }";
-            let prog = ctx.create_program_from_source(src);
+            let prog = ctx.create_program_from_source(src).unwrap();
             match prog.build(device) {
                 Ok(_) => (),
                 Err(build_log) => {
@@ -466,11 +1217,11 @@ mod array {
                     panic!("");
                 }
             }
-            let k = prog.create_kernel("test");
+            let k = prog.create_kernel("test").unwrap();
 
-            k.set_arg(0, &a_cl);
-            let event = queue.enqueue_async_kernel(&k, (8isize, 8isize), None, ());
-            queue.read(&a_cl, &mut a, &event);
+            k.set_arg(0, &a_cl).unwrap();
+            let event = queue.enqueue_async_kernel(&k, (8isize, 8isize), None, ()).unwrap();
+            queue.read(&a_cl, &mut a, &event).unwrap();
 
             for x in 0usize .. 8usize {
                 for y in 0usize .. 8usize {
@@ -485,8 +1236,8 @@ mod array {
     {
         ::test_all_platforms_devices(&mut |_, ctx, queue| {
             let arr_in = Array3D::new(8, 8, 8, |x, y, z| {(x+y+z) as isize});
-            let arr_cl = ctx.create_buffer_from(&arr_in, CL_MEM_READ_WRITE);
-            let arr_out: Array3D<isize> = queue.get(&arr_cl, ());
+            let arr_cl = ctx.create_buffer_from(&arr_in, CL_MEM_READ_WRITE).unwrap();
+            let arr_out: Array3D<isize> = queue.get(&arr_cl, ()).unwrap();
 
             for x in 0usize .. 8usize {
                 for y in 0usize .. 8usize {
@@ -508,10 +1259,10 @@ mod array {
             let mut out = Array3D::new(8, 8, 8, |_, _, _| {(0) as isize});
 
             /* both are zeroed */
-            let a_cl = ctx.create_buffer_from(&zero, CL_MEM_READ_WRITE);
+            let a_cl = ctx.create_buffer_from(&zero, CL_MEM_READ_WRITE).unwrap();
 
-            queue.write(&a_cl, &added, ());
-            queue.read(&a_cl, &mut out, ());
+            queue.write(&a_cl, &added, ()).unwrap();
+            queue.read(&a_cl, &mut out, ()).unwrap();
 
             for x in 0usize .. 8usize {
                 for y in 0usize .. 8usize {
@@ -530,7 +1281,7 @@ mod array {
         ::test_all_platforms_devices(&mut |device, ctx, queue| {
             let mut a = Array3D::new(8, 8, 8, |_, _, _| {(0) as i32});
             let b = Array3D::new(8, 8, 8, |x, y, z| {(x*y*z) as i32});
-            let a_cl = ctx.create_buffer_from(&a, CL_MEM_READ_WRITE);
+            let a_cl = ctx.create_buffer_from(&a, CL_MEM_READ_WRITE).unwrap();
 
             let src =  "__kernel void test(__global int *a) { \
                             int x = get_global_id(0); \
@@ -540,7 +1291,7 @@ mod array {
                             int size_y = get_global_size(1); \
                             a[size_x*size_y*z + size_x*y + x] = x*y*z; \
                         }";
-            let prog = ctx.create_program_from_source(src);
+            let prog = ctx.create_program_from_source(src).unwrap();
             match prog.build(device) {
                 Ok(_) => (),
                 Err(build_log) => {
@@ -549,11 +1300,11 @@ mod array {
                     panic!("");
                 }
             }
-            let k = prog.create_kernel("test");
+            let k = prog.create_kernel("test").unwrap();
 
-            k.set_arg(0, &a_cl);
-            let event = queue.enqueue_async_kernel(&k, (8isize, 8isize, 8isize), None, ());
-            queue.read(&a_cl, &mut a, &event);
+            k.set_arg(0, &a_cl).unwrap();
+            let event = queue.enqueue_async_kernel(&k, (8isize, 8isize, 8isize), None, ()).unwrap();
+            queue.read(&a_cl, &mut a, &event).unwrap();
 
             for x in 0usize .. 8usize {
                 for y in 0usize .. 8usize {
@@ -573,14 +1324,13 @@ mod ext {
 
     #[test]
     fn try_load_all_extensions() {
-        let platforms = get_platforms();
+        let platforms = get_platforms().unwrap();
 
         for platform in platforms.into_iter() {
-            let platform_id = platform.get_id();
 
             macro_rules! check_ext {
                 ($ext:ident) => {
-                    match ext::$ext::load(platform_id) {
+                    match ext::$ext::load(&platform) {
                         Ok(_) => {
                             info!("Extension {} loaded successfully.",
                                   stringify!($ext))
@@ -621,3 +1371,24 @@ mod cl {
         expect!(y.to_string(), "CL_DEVICE_NOT_FOUND");
     }
 }
+
+#[cfg(test)]
+mod error {
+    use opencl::Error;
+    use opencl::cl::cl_int;
+    use opencl::cl::CLStatus::*;
+
+    #[test]
+    fn error_status() {
+        let e = Error::new(CL_OUT_OF_RESOURCES as cl_int, "clEnqueueNDRangeKernel",
+                           "Error enqueuing kernel.");
+        expect!(e.status(), Some(CL_OUT_OF_RESOURCES));
+        expect!(e.api(), "clEnqueueNDRangeKernel");
+        expect!(e.to_string(),
+                "Error enqueuing kernel. (clEnqueueNDRangeKernel: CL_OUT_OF_RESOURCES)");
+
+        let e = Error::new(-1057, "clCreateSubDevicesEXT", "Partitioning failed");
+        expect!(e.status(), None);
+        expect!(e.status_code(), -1057);
+    }
+}